@@ -0,0 +1,157 @@
+use crate::Point;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Dense overlap counts covering all points from the origin to the furthest segment point
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    counts: Vec<usize>,
+}
+
+impl Heatmap {
+    pub fn new(overlaps: &HashMap<Point, usize>) -> Self {
+        let width = overlaps.keys().map(|p| p.x + 1).max().unwrap_or(0);
+        let height = overlaps.keys().map(|p| p.y + 1).max().unwrap_or(0);
+        let mut counts = vec![0; width * height];
+
+        for (point, count) in overlaps {
+            counts[point.y * width + point.x] = *count;
+        }
+
+        Self {
+            width,
+            height,
+            counts,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or(0).max(1)
+    }
+
+    /// Return the `k` points with the highest count, ties ordered by row and column
+    pub fn hotspots(&self, k: usize) -> Vec<(Point, usize)> {
+        let mut spots = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(i, &count)| (Point::new(i % self.width, i / self.width), count))
+            .collect::<Vec<_>>();
+
+        spots.sort_by(|(p, a), (q, b)| b.cmp(a).then((p.y, p.x).cmp(&(q.y, q.x))));
+        spots.truncate(k);
+        spots
+    }
+
+    /// Write a binary greyscale PGM with the count scaled to 0..=255
+    pub fn write_pgm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let max = self.max();
+
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+
+        let pixels = self
+            .counts
+            .iter()
+            .map(|&count| (count * 255 / max) as u8)
+            .collect::<Vec<_>>();
+
+        out.write_all(&pixels)
+    }
+
+    /// Write a binary PPM with a blue-to-yellow ramp and the hotspots drawn in green
+    pub fn write_ppm<W: Write>(&self, hotspots: &[Point], out: &mut W) -> io::Result<()> {
+        let max = self.max();
+
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        let mut pixels = Vec::with_capacity(self.counts.len() * 3);
+
+        for &count in &self.counts {
+            let t = count * 255 / max;
+
+            if count == 0 {
+                pixels.extend([0, 0, 0]);
+            } else {
+                pixels.extend([t as u8, (t * t / 255) as u8, (255 - t) as u8]);
+            }
+        }
+
+        for point in hotspots {
+            let i = (point.y * self.width + point.x) * 3;
+            pixels[i..i + 3].copy_from_slice(&[0, 255, 0]);
+        }
+
+        out.write_all(&pixels)
+    }
+
+    /// Render one character per point, `.` for none, the count or `+` above nine and `#` for
+    /// hotspots
+    pub fn ascii(&self, hotspots: &[Point]) -> String {
+        let mut result = String::with_capacity((self.width + 1) * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let count = self.counts[y * self.width + x];
+
+                result.push(if hotspots.contains(&Point::new(x, y)) {
+                    '#'
+                } else {
+                    match count {
+                        0 => '.',
+                        1..=9 => (b'0' + count as u8) as char,
+                        _ => '+',
+                    }
+                });
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() -> io::Result<()> {
+        let overlaps = HashMap::from([
+            (Point::new(0, 0), 1),
+            (Point::new(1, 0), 2),
+            (Point::new(2, 1), 4),
+        ]);
+
+        let heatmap = Heatmap::new(&overlaps);
+        let hotspots = heatmap
+            .hotspots(1)
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+
+        assert_eq!(hotspots, vec![Point::new(2, 1)]);
+        assert_eq!(heatmap.ascii(&hotspots), "12.\n..#\n");
+
+        let mut pgm = vec![];
+        heatmap.write_pgm(&mut pgm)?;
+        assert_eq!(pgm, b"P5\n3 2\n255\n\x3f\x7f\x00\x00\x00\xff");
+
+        let mut ppm = vec![];
+        heatmap.write_ppm(&hotspots, &mut ppm)?;
+        assert_eq!(ppm.len(), b"P6\n3 2\n255\n".len() + 3 * 6);
+        assert_eq!(&ppm[ppm.len() - 3..], &[0, 255, 0]);
+
+        Ok(())
+    }
+}
@@ -1,9 +1,16 @@
+mod heatmap;
+
+use heatmap::Heatmap;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader, BufWriter, Lines};
+use std::str::FromStr;
+
+/// Largest width or height still printed as ASCII density map
+const MAX_ASCII_SIZE: usize = 200;
 
 #[derive(Debug, Clone)]
 struct ParseError;
@@ -28,6 +35,45 @@ impl Point {
 
 impl Error for ParseError {}
 
+#[derive(Debug, Clone, PartialEq)]
+enum SegmentError {
+    /// Start and end point coincide
+    Degenerate(Point),
+    /// Neither horizontal, vertical nor a 45° diagonal
+    Unaligned(Point, Point),
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SegmentError::Degenerate(p) => write!(f, "Segment at {},{} has no length", p.x, p.y),
+            SegmentError::Unaligned(s, e) => write!(
+                f,
+                "Segment {},{} -> {},{} is not horizontal, vertical or diagonal",
+                s.x, s.y, e.x, e.y
+            ),
+        }
+    }
+}
+
+impl Error for SegmentError {}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Rasterisation {
+    /// Closest grid points along the line
+    Bresenham,
+    /// Only grid points lying exactly on the line
+    Lattice,
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 struct Segment {
     start: Point,
     end: Point,
@@ -40,6 +86,73 @@ impl Segment {
             && self.start.x != self.end.x
             && self.start.y != self.end.y
     }
+
+    fn axis_aligned(&self) -> bool {
+        self.start.x == self.end.x || self.start.y == self.end.y
+    }
+
+    /// Check that the segment can be handled by the given engine
+    fn validate(&self, engine: Engine) -> Result<(), SegmentError> {
+        if self.start == self.end {
+            return Err(SegmentError::Degenerate(self.start));
+        }
+
+        if engine == Engine::Sweep && !self.axis_aligned() && !self.diagonal() {
+            return Err(SegmentError::Unaligned(self.start, self.end));
+        }
+
+        Ok(())
+    }
+
+    fn points(&self, rasterisation: Rasterisation) -> Vec<Point> {
+        let (x0, y0) = (self.start.x as isize, self.start.y as isize);
+        let (x1, y1) = (self.end.x as isize, self.end.y as isize);
+        let mut points = vec![];
+
+        match rasterisation {
+            Rasterisation::Lattice => {
+                let steps = gcd((x1 - x0).unsigned_abs(), (y1 - y0).unsigned_abs()) as isize;
+                let (dx, dy) = if steps == 0 {
+                    (0, 0)
+                } else {
+                    ((x1 - x0) / steps, (y1 - y0) / steps)
+                };
+
+                for i in 0..=steps {
+                    points.push(Point::new((x0 + i * dx) as usize, (y0 + i * dy) as usize));
+                }
+            }
+            Rasterisation::Bresenham => {
+                let dx = (x1 - x0).abs();
+                let dy = -(y1 - y0).abs();
+                let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+                let (mut x, mut y) = (x0, y0);
+                let mut err = dx + dy;
+
+                loop {
+                    points.push(Point::new(x as usize, y as usize));
+
+                    if x == x1 && y == y1 {
+                        break;
+                    }
+
+                    let e2 = 2 * err;
+
+                    if e2 >= dy {
+                        err += dy;
+                        x += sx;
+                    }
+
+                    if e2 <= dx {
+                        err += dx;
+                        y += sy;
+                    }
+                }
+            }
+        }
+
+        points
+    }
 }
 
 impl TryFrom<&str> for Segment {
@@ -93,48 +206,204 @@ fn parse_segments<B: BufRead>(lines: &mut Lines<B>) -> Result<Vec<Segment>, Pars
         .collect::<Result<Vec<Segment>, _>>()?)
 }
 
-fn solve<'a, I>(segments: I) -> usize
+/// Count how many segments cover each point
+fn overlaps<'a, I>(segments: I, rasterisation: Rasterisation) -> HashMap<Point, usize>
 where
     I: Iterator<Item = &'a Segment>,
 {
     let mut acc = HashMap::<Point, usize>::new();
 
-    for Segment { start, end } in segments {
-        let dx = if start.x > end.x {
-            -1
-        } else if start.x < end.x {
-            1
-        } else {
-            0
-        };
-        let dy = if start.y > end.y {
-            -1
-        } else if start.y < end.y {
-            1
-        } else {
-            0
-        };
+    for segment in segments {
+        for point in segment.points(rasterisation) {
+            *acc.entry(point).or_insert(0) += 1;
+        }
+    }
 
-        let mut x = start.x as isize;
-        let mut y = start.y as isize;
+    acc
+}
 
-        while x != end.x as isize || y != end.y as isize {
-            *acc.entry(Point::new(x as usize, y as usize)).or_insert(0) += 1;
-            x += dx;
-            y += dy;
+fn solve<'a, I>(segments: I, rasterisation: Rasterisation) -> usize
+where
+    I: Iterator<Item = &'a Segment>,
+{
+    overlaps(segments, rasterisation)
+        .values()
+        .filter(|&c| c >= &2)
+        .count()
+}
+
+/// Count overlapping points column by column instead of rasterising all segments.
+///
+/// Only the segments crossing the current column are kept around, so memory is bounded by the
+/// number of segments rather than by their length.
+fn sweep<'a, I>(segments: I) -> usize
+where
+    I: Iterator<Item = &'a Segment>,
+{
+    let mut segments = segments
+        .map(|s| {
+            if (s.start.x, s.start.y) <= (s.end.x, s.end.y) {
+                (s.start, s.end)
+            } else {
+                (s.end, s.start)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    segments.sort_unstable_by_key(|(start, _)| start.x);
+
+    let mut active: Vec<(Point, Point)> = vec![];
+    let mut next = 0;
+    let mut events = vec![];
+    let mut count = 0;
+    let mut x = 0;
+
+    while next < segments.len() || !active.is_empty() {
+        if active.is_empty() {
+            x = segments[next].0.x;
         }
 
-        *acc.entry(Point::new(x as usize, y as usize)).or_insert(0) += 1;
+        while next < segments.len() && segments[next].0.x == x {
+            active.push(segments[next]);
+            next += 1;
+        }
+
+        events.clear();
+
+        for (start, end) in &active {
+            let (lower, upper) = if start.x == end.x {
+                (start.y, end.y)
+            } else {
+                let offset = x - start.x;
+                let y = if start.y < end.y {
+                    start.y + offset
+                } else if start.y > end.y {
+                    start.y - offset
+                } else {
+                    start.y
+                };
+                (y, y)
+            };
+
+            events.push((lower, 1isize));
+            events.push((upper + 1, -1isize));
+        }
+
+        events.sort_unstable();
+
+        let mut depth = 0;
+
+        for (i, (y, delta)) in events.iter().enumerate() {
+            depth += delta;
+
+            if depth >= 2 {
+                count += events[i + 1].0 - y;
+            }
+        }
+
+        active.retain(|(_, end)| end.x > x);
+        x += 1;
     }
 
-    acc.values().filter(|&c| c >= &2).count()
+    count
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Engine {
+    Raster(Rasterisation),
+    Sweep,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raster" | "lattice" => Ok(Engine::Raster(Rasterisation::Lattice)),
+            "bresenham" => Ok(Engine::Raster(Rasterisation::Bresenham)),
+            "sweep" => Ok(Engine::Sweep),
+            _ => Err(format!("Unknown engine {}", s)),
+        }
+    }
+}
+
+fn solve_with<'a, I>(engine: Engine, segments: I) -> Result<usize, SegmentError>
+where
+    I: Iterator<Item = &'a Segment> + Clone,
+{
+    for segment in segments.clone() {
+        segment.validate(engine)?;
+    }
+
+    Ok(match engine {
+        Engine::Raster(rasterisation) => solve(segments, rasterisation),
+        Engine::Sweep => sweep(segments),
+    })
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = Engine::Raster(Rasterisation::Lattice);
+    let mut pgm = None;
+    let mut ppm = None;
+    let mut ascii = false;
+    let mut num_hotspots = 0;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--engine" => engine = args.next().ok_or("--engine requires a value")?.parse()?,
+            "--pgm" => pgm = Some(args.next().ok_or("--pgm requires a path")?),
+            "--ppm" => ppm = Some(args.next().ok_or("--ppm requires a path")?),
+            "--ascii" => ascii = true,
+            "--hotspots" => {
+                num_hotspots = args.next().ok_or("--hotspots requires a number")?.parse()?
+            }
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+
     let reader = BufReader::new(File::open("input")?);
     let segments = parse_segments(&mut reader.lines())?;
-    println!("{}", solve(segments.iter().filter(|&s| !s.diagonal())));
-    println!("{}", solve(segments.iter()));
+    println!(
+        "{}",
+        solve_with(engine, segments.iter().filter(|&s| s.axis_aligned()))?
+    );
+    println!("{}", solve_with(engine, segments.iter())?);
+
+    if pgm.is_some() || ppm.is_some() || ascii {
+        let rasterisation = match engine {
+            Engine::Raster(rasterisation) => rasterisation,
+            Engine::Sweep => Rasterisation::Lattice,
+        };
+
+        let heatmap = Heatmap::new(&overlaps(segments.iter(), rasterisation));
+        let hotspots = heatmap
+            .hotspots(num_hotspots)
+            .into_iter()
+            .map(|(point, _)| point)
+            .collect::<Vec<_>>();
+
+        if let Some(path) = pgm {
+            heatmap.write_pgm(&mut BufWriter::new(File::create(path)?))?;
+        }
+
+        if let Some(path) = ppm {
+            heatmap.write_ppm(&hotspots, &mut BufWriter::new(File::create(path)?))?;
+        }
+
+        if ascii {
+            if heatmap.width() > MAX_ASCII_SIZE || heatmap.height() > MAX_ASCII_SIZE {
+                return Err(format!(
+                    "Map of {}x{} is too large for ASCII output",
+                    heatmap.width(),
+                    heatmap.height()
+                )
+                .into());
+            }
+
+            print!("{}", heatmap.ascii(&hotspots));
+        }
+    }
 
     Ok(())
 }
@@ -168,8 +437,84 @@ mod tests {
         );
 
         let segments = parse_segments(&mut cursor.lines())?;
-        assert_eq!(solve(segments.iter().filter(|&s| !s.diagonal())), 5);
-        assert_eq!(solve(segments.iter()), 12);
+        let lattice = Rasterisation::Lattice;
+        assert_eq!(
+            solve(segments.iter().filter(|&s| !s.diagonal()), lattice),
+            5
+        );
+        assert_eq!(solve(segments.iter(), lattice), 12);
+        assert_eq!(solve(segments.iter(), Rasterisation::Bresenham), 12);
+        assert_eq!(sweep(segments.iter().filter(|&s| !s.diagonal())), 5);
+        assert_eq!(sweep(segments.iter()), 12);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweep_matches_raster() -> Result<(), SegmentError> {
+        let mut state = 0x2545f491u64;
+        let mut random = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+
+        let segments = (0..300)
+            .map(|_| {
+                // Degenerate segments are rejected by both engines
+                let length = random(19) + 1;
+                let start = Point::new(random(50), random(50) + length);
+                let end = match random(4) {
+                    0 => Point::new(start.x + length, start.y),
+                    1 => Point::new(start.x, start.y + length),
+                    2 => Point::new(start.x + length, start.y + length),
+                    _ => Point::new(start.x + length, start.y - length),
+                };
+                Segment { start, end }
+            })
+            .collect::<Vec<_>>();
+
+        let swept = solve_with(Engine::Sweep, segments.iter())?;
+        let rasterised = solve_with(Engine::Raster(Rasterisation::Lattice), segments.iter())?;
+        assert_eq!(swept, rasterised);
+        assert!(swept > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_arbitrary_angles() -> Result<(), Box<dyn std::error::Error>> {
+        let segment: Segment = "0,0 -> 6,4".try_into()?;
+        assert_eq!(
+            segment.points(Rasterisation::Lattice),
+            vec![Point::new(0, 0), Point::new(3, 2), Point::new(6, 4)]
+        );
+        assert_eq!(
+            segment.points(Rasterisation::Bresenham),
+            vec![
+                Point::new(0, 0),
+                Point::new(1, 1),
+                Point::new(2, 1),
+                Point::new(3, 2),
+                Point::new(4, 3),
+                Point::new(5, 3),
+                Point::new(6, 4),
+            ]
+        );
+
+        let segments: Vec<Segment> = vec!["0,0 -> 6,4".try_into()?, "6,0 -> 0,4".try_into()?];
+        let bresenham = Engine::Raster(Rasterisation::Bresenham);
+        assert_eq!(solve_with(bresenham, segments.iter()), Ok(1));
+        assert_eq!(
+            solve_with(Engine::Sweep, segments.iter()),
+            Err(SegmentError::Unaligned(Point::new(0, 0), Point::new(6, 4)))
+        );
+
+        let segments: Vec<Segment> = vec!["3,3 -> 3,3".try_into()?];
+        assert_eq!(
+            solve_with(bresenham, segments.iter()),
+            Err(SegmentError::Degenerate(Point::new(3, 3)))
+        );
 
         Ok(())
     }
@@ -0,0 +1,219 @@
+use crate::bigint::BigUint;
+use std::str::FromStr;
+
+/// Number of fish per timer value at a given day
+pub type Histogram = (usize, Vec<BigUint>);
+
+/// Timer rules of a single species
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lifecycle {
+    /// Timer value of a newly born fish
+    pub newborn: usize,
+    /// Timer value of a fish after it gave birth
+    pub reset: usize,
+    /// Number of days after which a fish dies, if ever
+    pub death: Option<usize>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            newborn: 8,
+            reset: 6,
+            death: None,
+        }
+    }
+}
+
+impl Lifecycle {
+    /// Number of distinct timer values
+    pub fn timers(&self) -> usize {
+        self.newborn.max(self.reset) + 1
+    }
+
+    /// Number of distinct (age, timer) states, ages are only tracked if fish can die
+    pub fn states(&self) -> usize {
+        self.death.unwrap_or(1) * self.timers()
+    }
+
+    /// States a fish in `state` turns into after one day, a fish giving birth on the day it dies
+    /// still leaves its offspring
+    pub fn successors(&self, state: usize) -> Vec<usize> {
+        let (age, timer) = (state / self.timers(), state % self.timers());
+        let age = match self.death {
+            Some(death) if age + 1 >= death => None,
+            Some(_) => Some(age + 1),
+            None => Some(0),
+        };
+
+        match (age, timer) {
+            (Some(age), 0) => vec![age * self.timers() + self.reset, self.newborn],
+            (None, 0) => vec![self.newborn],
+            (Some(age), timer) => vec![age * self.timers() + timer - 1],
+            (None, _) => vec![],
+        }
+    }
+}
+
+/// Number of fish per state
+pub struct Population<'a> {
+    lifecycle: &'a Lifecycle,
+    counts: Vec<BigUint>,
+}
+
+impl<'a> Population<'a> {
+    /// Parse comma-separated timers, all initial fish have age zero
+    pub fn new(
+        lifecycle: &'a Lifecycle,
+        initial: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut counts = vec![BigUint::zero(); lifecycle.states()];
+
+        for timer in initial.trim().split(',').map(|x| x.parse()) {
+            let timer: usize = timer?;
+
+            if timer >= lifecycle.timers() {
+                return Err(format!("Timer {} exceeds lifecycle", timer).into());
+            }
+
+            counts[timer] += &BigUint::from(1);
+        }
+
+        Ok(Self { lifecycle, counts })
+    }
+
+    pub fn counts(&self) -> &[BigUint] {
+        &self.counts
+    }
+
+    pub fn step(&mut self) {
+        let mut next = vec![BigUint::zero(); self.counts.len()];
+
+        for (state, count) in self.counts.iter().enumerate() {
+            if !count.is_zero() {
+                for successor in self.lifecycle.successors(state) {
+                    next[successor] += count;
+                }
+            }
+        }
+
+        self.counts = next;
+    }
+
+    pub fn total(&self) -> BigUint {
+        let mut total = BigUint::zero();
+
+        for count in &self.counts {
+            total += count;
+        }
+
+        total
+    }
+
+    /// Number of fish per timer value regardless of their age
+    pub fn histogram(&self) -> Vec<BigUint> {
+        let mut histogram = vec![BigUint::zero(); self.lifecycle.timers()];
+
+        for (state, count) in self.counts.iter().enumerate() {
+            histogram[state % self.lifecycle.timers()] += count;
+        }
+
+        histogram
+    }
+}
+
+/// Named lifecycle with its initial population
+pub struct Species {
+    pub name: String,
+    pub lifecycle: Lifecycle,
+    pub initial: String,
+}
+
+impl Species {
+    /// Histograms at each of the given days in ascending order
+    pub fn histograms(&self, days: &[usize]) -> Result<Vec<Histogram>, Box<dyn std::error::Error>> {
+        let mut days = days.to_vec();
+        days.sort_unstable();
+
+        let mut population = Population::new(&self.lifecycle, &self.initial)?;
+        let mut current = 0;
+        let mut result = vec![];
+
+        for day in days {
+            while current < day {
+                population.step();
+                current += 1;
+            }
+
+            result.push((day, population.histogram()));
+        }
+
+        Ok(result)
+    }
+}
+
+impl FromStr for Species {
+    type Err = Box<dyn std::error::Error>;
+
+    /// Parse `name newborn reset death timers` where death is `-` for immortal fish
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+
+        if fields.len() != 5 {
+            return Err(format!("Expected five fields in {:?}", s).into());
+        }
+
+        let death = match fields[3] {
+            "-" => None,
+            death => match death.parse()? {
+                0 => return Err("Death age must be positive".into()),
+                death => Some(death),
+            },
+        };
+
+        Ok(Self {
+            name: fields[0].to_string(),
+            lifecycle: Lifecycle {
+                newborn: fields[1].parse()?,
+                reset: fields[2].parse()?,
+                death,
+            },
+            initial: fields[4].to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_species() -> Result<(), Box<dyn std::error::Error>> {
+        let species: Species = "lanternfish 8 6 - 3,4,3,1,2".parse()?;
+        assert_eq!(species.lifecycle, Lifecycle::default());
+
+        let histograms = species.histograms(&[18, 1])?;
+        assert_eq!(histograms[0].0, 1);
+        assert_eq!(histograms[1].0, 18);
+
+        let to_string = |h: &[BigUint]| h.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            to_string(&histograms[0].1),
+            ["1", "1", "2", "1", "0", "0", "0", "0", "0"]
+        );
+        assert_eq!(
+            to_string(&histograms[1].1),
+            ["3", "5", "3", "2", "2", "1", "5", "1", "4"]
+        );
+
+        // Fish die before their timer runs out, so nothing is left after three days
+        let species: Species = "mayfly 4 2 3 3,4".parse()?;
+        let histograms = species.histograms(&[2, 3])?;
+        assert_eq!(to_string(&histograms[0].1), ["0", "1", "1", "0", "0"]);
+        assert_eq!(to_string(&histograms[1].1), ["0", "0", "0", "0", "0"]);
+
+        assert!("mayfly 4 2 0 3".parse::<Species>().is_err());
+        assert!(Population::new(&Lifecycle::default(), "9").is_err());
+        Ok(())
+    }
+}
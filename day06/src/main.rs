@@ -1,26 +1,112 @@
+mod bigint;
+mod lifecycle;
+
+use bigint::BigUint;
+use lifecycle::{Lifecycle, Population, Species};
+use std::convert::TryFrom;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
+
+type Matrix = Vec<Vec<BigUint>>;
 
 fn number_of_fish(initial: &str, num_days: usize) -> Result<usize, Box<dyn std::error::Error>> {
-    let mut stock = [0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let lifecycle = Lifecycle::default();
+    let mut population = Population::new(&lifecycle, initial)?;
 
-    for timer in initial.trim().split(',').map(|x| x.parse()) {
-        let timer: usize = timer?;
-        stock[timer] += 1;
+    for _ in 0..num_days {
+        population.step();
     }
 
-    for _ in 0..num_days {
-        let values = stock.clone();
-        let xs = 0..9;
+    Ok(population
+        .total()
+        .to_u64()
+        .and_then(|total| usize::try_from(total).ok())
+        .ok_or("Number of fish does not fit into usize")?)
+}
+
+/// Matrix mapping the number of fish per state from one day to the next
+fn transition(lifecycle: &Lifecycle) -> Matrix {
+    let mut matrix = vec![vec![BigUint::zero(); lifecycle.states()]; lifecycle.states()];
+
+    let edges = (0..lifecycle.states()).flat_map(|state| {
+        lifecycle
+            .successors(state)
+            .into_iter()
+            .map(move |successor| (successor, state))
+    });
 
-        for (i, j) in xs.clone().zip(xs.cycle().skip(1)) {
-            stock[i] = values[j];
+    for (successor, state) in edges {
+        matrix[successor][state] += &BigUint::from(1);
+    }
+
+    matrix
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    (0..a.len())
+        .map(|i| {
+            (0..b[0].len())
+                .map(|j| {
+                    let mut sum = BigUint::zero();
+
+                    for k in 0..b.len() {
+                        if !a[i][k].is_zero() && !b[k][j].is_zero() {
+                            sum += &(&a[i][k] * &b[k][j]);
+                        }
+                    }
+
+                    sum
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Raise the matrix to the n-th power by repeated squaring
+fn power(matrix: &Matrix, mut n: usize) -> Matrix {
+    let mut result = (0..matrix.len())
+        .map(|i| {
+            (0..matrix.len())
+                .map(|j| BigUint::from((i == j) as u64))
+                .collect()
+        })
+        .collect();
+    let mut base = matrix.clone();
+
+    while n > 0 {
+        if n & 1 == 1 {
+            result = multiply(&result, &base);
         }
 
-        stock[6] += values[0];
+        n >>= 1;
+
+        if n > 0 {
+            base = multiply(&base, &base);
+        }
     }
 
-    Ok(stock.iter().sum())
+    result
+}
+
+/// Exact number of fish after `num_days` computed in O(log num_days) matrix multiplications
+fn number_of_fish_exact(
+    lifecycle: &Lifecycle,
+    initial: &str,
+    num_days: usize,
+) -> Result<BigUint, Box<dyn std::error::Error>> {
+    let population = Population::new(lifecycle, initial)?;
+    let matrix = power(&transition(lifecycle), num_days);
+    let mut total = BigUint::zero();
+
+    for row in &matrix {
+        for (entry, count) in row.iter().zip(population.counts()) {
+            if !count.is_zero() {
+                total += &(entry * count);
+            }
+        }
+    }
+
+    Ok(total)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -28,6 +114,53 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     File::open("input")?.read_to_string(&mut input)?;
     println!("{}", number_of_fish(&input, 80)?);
     println!("{}", number_of_fish(&input, 256)?);
+
+    let mut species = None;
+    let mut days = vec![];
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--species" => species = Some(args.next().ok_or("--species requires a path")?),
+            "--days" => {
+                days = args
+                    .next()
+                    .ok_or("--days requires a list of days")?
+                    .split(',')
+                    .map(|x| x.parse())
+                    .collect::<Result<Vec<usize>, _>>()?
+            }
+            num_days => {
+                let num_days = num_days.parse()?;
+                println!(
+                    "{}",
+                    number_of_fish_exact(&Lifecycle::default(), &input, num_days)?
+                );
+            }
+        }
+    }
+
+    if let Some(path) = species {
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let species: Species = line.parse()?;
+
+            for (day, histogram) in species.histograms(&days)? {
+                let histogram = histogram
+                    .iter()
+                    .map(|count| count.to_string())
+                    .collect::<Vec<_>>();
+
+                println!("{} {}: {}", species.name, day, histogram.join(" "));
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -42,4 +175,53 @@ mod tests {
         assert_eq!(number_of_fish(input, 256)?, 26984457539);
         Ok(())
     }
+
+    #[test]
+    fn test_exact() -> Result<(), Box<dyn std::error::Error>> {
+        let input = "3,4,3,1,2";
+        let lifecycle = Lifecycle::default();
+        let exact = |num_days| number_of_fish_exact(&lifecycle, input, num_days);
+        assert_eq!(exact(0)?.to_string(), "5");
+        assert_eq!(exact(18)?.to_string(), "26");
+        assert_eq!(exact(256)?.to_string(), "26984457539");
+
+        // Compare against a day by day simulation that cannot overflow
+        let mut stock: [BigUint; 9] = std::array::from_fn(|_| BigUint::zero());
+
+        for timer in input.split(',') {
+            stock[timer.parse::<usize>()?] += &BigUint::from(1);
+        }
+
+        for _ in 0..2000 {
+            stock.rotate_left(1);
+            let born = stock[8].clone();
+            stock[6] += &born;
+        }
+
+        let mut expected = BigUint::zero();
+
+        for count in &stock {
+            expected += count;
+        }
+
+        assert_eq!(exact(2000)?, expected);
+
+        // Transition matrix also covers lifecycles with a limited lifetime
+        let lifecycle = Lifecycle {
+            newborn: 4,
+            reset: 2,
+            death: Some(7),
+        };
+        let mut population = Population::new(&lifecycle, input)?;
+
+        for _ in 0..50 {
+            population.step();
+        }
+
+        assert_eq!(
+            number_of_fish_exact(&lifecycle, input, 50)?,
+            population.total()
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,261 @@
+use std::error::Error;
+
+/// Fuel needed to move a crab between two positions
+pub trait CostModel {
+    /// Cost for the crab at index `crab` to move from `from` to `to`
+    fn cost(&self, crab: usize, from: usize, to: usize) -> usize;
+
+    /// Number of crabs the model is set up for, if it depends on the crab
+    fn crabs(&self) -> Option<usize> {
+        None
+    }
+}
+
+fn distance(from: usize, to: usize) -> usize {
+    from.max(to) - from.min(to)
+}
+
+/// One unit of fuel per step
+pub struct Linear;
+
+impl CostModel for Linear {
+    fn cost(&self, _: usize, from: usize, to: usize) -> usize {
+        distance(from, to)
+    }
+}
+
+/// Each step costs one more than the previous one
+pub struct Triangular;
+
+impl CostModel for Triangular {
+    fn cost(&self, _: usize, from: usize, to: usize) -> usize {
+        let n = distance(from, to);
+        (n * n + n) / 2
+    }
+}
+
+/// Square of the distance
+pub struct Quadratic;
+
+impl CostModel for Quadratic {
+    fn cost(&self, _: usize, from: usize, to: usize) -> usize {
+        distance(from, to).pow(2)
+    }
+}
+
+/// Never more than `cap` for a single crab
+pub struct Capped {
+    pub cap: usize,
+    pub inner: Box<dyn CostModel>,
+}
+
+impl CostModel for Capped {
+    fn cost(&self, crab: usize, from: usize, to: usize) -> usize {
+        self.inner.cost(crab, from, to).min(self.cap)
+    }
+
+    fn crabs(&self) -> Option<usize> {
+        self.inner.crabs()
+    }
+}
+
+/// Scales the cost of each crab by its own weight
+pub struct Weighted {
+    pub weights: Vec<usize>,
+    pub inner: Box<dyn CostModel>,
+}
+
+impl CostModel for Weighted {
+    fn cost(&self, crab: usize, from: usize, to: usize) -> usize {
+        self.weights[crab] * self.inner.cost(crab, from, to)
+    }
+
+    fn crabs(&self) -> Option<usize> {
+        Some(self.weights.len())
+    }
+}
+
+/// Fuel per step depends on how far the crab has already moved, each `(start, rate)` pair applies
+/// `rate` to all steps beyond `start` until the next pair takes over
+pub struct Piecewise {
+    pub steps: Vec<(usize, usize)>,
+}
+
+impl CostModel for Piecewise {
+    fn cost(&self, _: usize, from: usize, to: usize) -> usize {
+        let n = distance(from, to);
+
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, &(start, rate))| {
+                let end = self.steps.get(i + 1).map_or(n, |&(next, _)| next.min(n));
+                end.saturating_sub(start) * rate
+            })
+            .sum()
+    }
+}
+
+/// Parse a model description such as `linear`, `capped 10 triangular`, `weighted 1,2,3 linear`
+/// or `piecewise 0:1 10:3`, lines starting with `#` are ignored
+pub fn parse_model(spec: &str) -> Result<Box<dyn CostModel>, Box<dyn Error>> {
+    let tokens = spec
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split_whitespace())
+        .collect::<Vec<_>>();
+
+    let (model, rest) = parse_tokens(&tokens)?;
+
+    if !rest.is_empty() {
+        return Err(format!("Unexpected {:?} after model", rest.join(" ")).into());
+    }
+
+    Ok(model)
+}
+
+/// Parsed model and the tokens following it
+type Parsed<'a, 'b> = (Box<dyn CostModel>, &'b [&'a str]);
+
+fn parse_tokens<'a, 'b>(tokens: &'b [&'a str]) -> Result<Parsed<'a, 'b>, Box<dyn Error>> {
+    let (name, rest) = tokens.split_first().ok_or("Missing model name")?;
+
+    match *name {
+        "linear" => Ok((Box::new(Linear), rest)),
+        "triangular" => Ok((Box::new(Triangular), rest)),
+        "quadratic" => Ok((Box::new(Quadratic), rest)),
+        "capped" => {
+            let (cap, rest) = rest.split_first().ok_or("capped requires a limit")?;
+            let (inner, rest) = parse_tokens(rest)?;
+            let cap = cap.parse()?;
+            Ok((Box::new(Capped { cap, inner }), rest))
+        }
+        "weighted" => {
+            let (weights, rest) = rest.split_first().ok_or("weighted requires weights")?;
+            let (inner, rest) = parse_tokens(rest)?;
+            let weights = weights
+                .split(',')
+                .map(|w| w.parse())
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if let Some(crabs) = inner.crabs() {
+                if crabs != weights.len() {
+                    return Err(format!(
+                        "weighted has {} weights but wraps a model for {} crabs",
+                        weights.len(),
+                        crabs
+                    )
+                    .into());
+                }
+            }
+
+            Ok((Box::new(Weighted { weights, inner }), rest))
+        }
+        "piecewise" => {
+            let count = rest.iter().take_while(|t| t.contains(':')).count();
+            let mut steps = rest[..count]
+                .iter()
+                .map(|t| {
+                    let (start, rate) = t.split_once(':').unwrap();
+                    Ok((start.parse()?, rate.parse()?))
+                })
+                .collect::<Result<Vec<(usize, usize)>, Box<dyn Error>>>()?;
+
+            if steps.is_empty() {
+                return Err("piecewise requires at least one start:rate pair".into());
+            }
+
+            steps.sort_unstable();
+            Ok((Box::new(Piecewise { steps }), &rest[count..]))
+        }
+        name => Err(format!("Unknown cost model {}", name).into()),
+    }
+}
+
+/// Optimal positions and the total cost for every position between the outermost crabs
+pub struct Report {
+    pub best: usize,
+    pub positions: Vec<usize>,
+    pub curve: Vec<(usize, usize)>,
+}
+
+pub fn report(model: &dyn CostModel, pos: &[usize]) -> Result<Report, Box<dyn Error>> {
+    if let Some(crabs) = model.crabs() {
+        if crabs != pos.len() {
+            return Err(format!("Model is set up for {} crabs, got {}", crabs, pos.len()).into());
+        }
+    }
+
+    let lower = *pos.iter().min().ok_or("No crabs given")?;
+    let upper = *pos.iter().max().ok_or("No crabs given")?;
+
+    let curve = (lower..(upper + 1))
+        .map(|target| {
+            let cost = pos
+                .iter()
+                .enumerate()
+                .map(|(crab, &x)| model.cost(crab, x, target))
+                .sum();
+            (target, cost)
+        })
+        .collect::<Vec<_>>();
+
+    let best = curve.iter().map(|&(_, cost)| cost).min().unwrap();
+    let positions = curve
+        .iter()
+        .filter(|&&(_, cost)| cost == best)
+        .map(|&(target, _)| target)
+        .collect();
+
+    Ok(Report {
+        best,
+        positions,
+        curve,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models() -> Result<(), Box<dyn Error>> {
+        let input = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+        let linear = report(parse_model("linear")?.as_ref(), &input)?;
+        assert_eq!(linear.best, 37);
+        assert_eq!(linear.positions, vec![2]);
+        assert_eq!(linear.curve.len(), 17);
+        assert_eq!(linear.curve[1], (1, 41));
+
+        let triangular = report(parse_model("# part two\ntriangular")?.as_ref(), &input)?;
+        assert_eq!(triangular.best, 168);
+
+        // Same as triangular for the first steps, then a flat rate
+        let piecewise = parse_model("piecewise 0:1 1:2 2:3")?;
+        assert_eq!(piecewise.cost(0, 0, 2), 3);
+        assert_eq!(piecewise.cost(0, 5, 0), 12);
+
+        let capped = parse_model("capped 4 quadratic")?;
+        assert_eq!(capped.cost(0, 0, 1), 1);
+        assert_eq!(capped.cost(0, 0, 3), 4);
+
+        let weighted = report(parse_model("weighted 1,3 linear")?.as_ref(), &[0, 10])?;
+        assert_eq!(weighted.best, 10);
+        assert_eq!(weighted.positions, vec![10]);
+
+        // Weights must match the crabs, also when wrapped in another model
+        let weighted = parse_model("capped 5 weighted 1,3 linear")?;
+        assert!(report(weighted.as_ref(), &[0, 10, 4]).is_err());
+        assert!(report(weighted.as_ref(), &[0]).is_err());
+        assert!(parse_model("weighted 1,1,1 weighted 1,2 linear").is_err());
+
+        let nested = parse_model("weighted 2,1 capped 3 weighted 1,3 linear")?;
+        assert_eq!(report(nested.as_ref(), &[0, 10])?.best, 3);
+
+        assert!(report(&Linear, &[]).is_err());
+        assert!(parse_model("capped linear").is_err());
+        assert!(parse_model("linear linear").is_err());
+        Ok(())
+    }
+}
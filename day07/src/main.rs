@@ -1,37 +1,81 @@
+mod cost;
+
 use std::fs::File;
 use std::io::Read;
 
-fn brute_force<F>(cost_fn: F, pos: &[usize]) -> usize
+fn linear(x: usize, y: usize) -> usize {
+    x.max(y) - x.min(y)
+}
+
+fn triangular(x: usize, y: usize) -> usize {
+    let n = linear(x, y);
+    (n * n + n) / 2
+}
+
+/// Total cost of moving all crabs to `target`
+fn total_cost<F>(cost_fn: &F, pos: &[usize], target: usize) -> usize
+where
+    F: Fn(usize, usize) -> usize,
+{
+    pos.iter().map(|&x| cost_fn(x, target)).sum()
+}
+
+fn brute_force<F>(cost_fn: F, pos: &[usize]) -> Option<usize>
 where
     F: Fn(usize, usize) -> usize,
 {
-    let lower = *pos.iter().min().unwrap();
-    let upper = *pos.iter().max().unwrap();
-    let mut best = usize::MAX;
+    let lower = *pos.iter().min()?;
+    let upper = *pos.iter().max()?;
 
-    for i in lower..(upper + 1) {
-        let cost = pos.iter().map(|&x| cost_fn(x, i)).sum();
+    (lower..(upper + 1))
+        .map(|i| total_cost(&cost_fn, pos, i))
+        .min()
+}
+
+/// Find the minimal total cost for a `cost_fn` that is convex in the target position, by binary
+/// searching for the point where the total cost stops decreasing
+fn convex_search<F>(cost_fn: F, pos: &[usize]) -> Option<usize>
+where
+    F: Fn(usize, usize) -> usize,
+{
+    let mut lower = *pos.iter().min()?;
+    let mut upper = *pos.iter().max()?;
 
-        if cost < best {
-            best = cost;
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+
+        if total_cost(&cost_fn, pos, mid) <= total_cost(&cost_fn, pos, mid + 1) {
+            upper = mid;
+        } else {
+            lower = mid + 1;
         }
     }
 
-    best
+    Some(total_cost(&cost_fn, pos, lower))
 }
 
-fn solve_part_one(pos: &[usize]) -> usize {
-    brute_force(|x, y| x.max(y) - x.min(y), pos)
+/// The sum of absolute distances is minimal at the median
+fn solve_part_one(pos: &[usize]) -> Option<usize> {
+    let mut sorted = pos.to_vec();
+    sorted.sort_unstable();
+    let median = *sorted.get(sorted.len() / 2)?;
+
+    Some(total_cost(&linear, pos, median))
 }
 
-fn solve_part_two(pos: &[usize]) -> usize {
-    brute_force(
-        |x, y| {
-            let n = x.max(y) - x.min(y);
-            (n * n + n) / 2
-        },
-        pos,
-    )
+/// The triangular cost is minimal within one half of the mean, so only its floor and ceiling
+/// need to be checked
+fn solve_part_two(pos: &[usize]) -> Option<usize> {
+    if pos.is_empty() {
+        return None;
+    }
+
+    let mean = pos.iter().sum::<usize>() / pos.len();
+
+    [mean, mean + 1]
+        .iter()
+        .map(|&target| total_cost(&triangular, pos, target))
+        .min()
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -44,8 +88,63 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .map(|x| x.parse())
         .collect::<Result<Vec<_>, _>>()?;
 
-    println!("{}", solve_part_one(&input));
-    println!("{}", solve_part_two(&input));
+    let mut verify = false;
+    let mut model = None;
+    let mut curve = false;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--verify" => verify = true,
+            "--curve" => curve = true,
+            "--model" => model = Some(args.next().ok_or("--model requires a model")?),
+            "--model-file" => {
+                let mut spec = String::new();
+                File::open(args.next().ok_or("--model-file requires a path")?)?
+                    .read_to_string(&mut spec)?;
+                model = Some(spec);
+            }
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+
+    let part_one = solve_part_one(&input).ok_or("No crabs given")?;
+    let part_two = solve_part_two(&input).ok_or("No crabs given")?;
+
+    println!("{}", part_one);
+    println!("{}", part_two);
+
+    if verify {
+        let expected = (brute_force(linear, &input), brute_force(triangular, &input));
+
+        if expected != (Some(part_one), Some(part_two))
+            || expected
+                != (
+                    convex_search(linear, &input),
+                    convex_search(triangular, &input),
+                )
+        {
+            return Err(format!("Brute force disagrees, expected {:?}", expected).into());
+        }
+    }
+
+    if let Some(spec) = model {
+        let model = cost::parse_model(&spec)?;
+        let report = cost::report(model.as_ref(), &input)?;
+        let positions = report
+            .positions
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+
+        println!("best {} at {}", report.best, positions.join(","));
+
+        if curve {
+            for (position, cost) in report.curve {
+                println!("{} {}", position, cost);
+            }
+        }
+    }
 
     Ok(())
 }
@@ -57,12 +156,36 @@ mod tests {
     #[test]
     fn text_example_part_one() {
         let input = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
-        assert_eq!(solve_part_one(&input), 37);
+        assert_eq!(solve_part_one(&input), Some(37));
+        assert_eq!(brute_force(linear, &input), Some(37));
+        assert_eq!(convex_search(linear, &input), Some(37));
     }
 
     #[test]
     fn text_example_part_two() {
         let input = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
-        assert_eq!(solve_part_two(&input), 168);
+        assert_eq!(solve_part_two(&input), Some(168));
+        assert_eq!(brute_force(triangular, &input), Some(168));
+        assert_eq!(convex_search(triangular, &input), Some(168));
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut state = 0x9e3779b9u64;
+        let mut random = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+
+        for len in 0..50 {
+            let input = (0..len).map(|_| random(100)).collect::<Vec<_>>();
+            let cubic = |x, y| linear(x, y).pow(3);
+
+            assert_eq!(solve_part_one(&input), brute_force(linear, &input));
+            assert_eq!(solve_part_two(&input), brute_force(triangular, &input));
+            assert_eq!(convex_search(cubic, &input), brute_force(cubic, &input));
+        }
     }
 }
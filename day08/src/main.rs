@@ -1,35 +1,123 @@
+mod solver;
+
+use solver::Display;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Lines};
+use std::ops::{BitAnd, BitOr, Sub};
+
+/// Set of wires or segments `a` to `g`, bit `i` stands for the `i`-th letter
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Signal(u8);
+
+impl Signal {
+    fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn contains(self, wire: usize) -> bool {
+        self.0 & 1 << wire != 0
+    }
 
-#[derive(Copy, Clone, Debug)]
-enum Candidate {
-    One([u8; 2]),
-    Four([u8; 4]),
-    Seven([u8; 3]),
-    Eight([u8; 7]),
-    UnknownFive([u8; 5]),
-    UnknownSix([u8; 6]),
-    Invalid,
+    fn wires(self) -> impl Iterator<Item = usize> {
+        (0..7).filter(move |&wire| self.contains(wire))
+    }
 }
 
-impl Candidate {
-    fn data(&self) -> &[u8] {
-        match self {
-            Candidate::One(x) => x,
-            Candidate::Four(x) => x,
-            Candidate::Seven(x) => x,
-            Candidate::Eight(x) => x,
-            Candidate::UnknownFive(x) => x,
-            Candidate::UnknownSix(x) => x,
-            Invalid => panic!("nononon"),
+impl BitAnd for Signal {
+    type Output = Signal;
+
+    fn bitand(self, other: Signal) -> Signal {
+        Signal(self.0 & other.0)
+    }
+}
+
+impl BitOr for Signal {
+    type Output = Signal;
+
+    fn bitor(self, other: Signal) -> Signal {
+        Signal(self.0 | other.0)
+    }
+}
+
+impl Sub for Signal {
+    type Output = Signal;
+
+    fn sub(self, other: Signal) -> Signal {
+        Signal(self.0 & !other.0)
+    }
+}
+
+impl TryFrom<&str> for Signal {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.bytes().try_fold(Signal::default(), |signal, c| match c {
+            b'a'..=b'g' if !signal.contains((c - b'a') as usize) => {
+                Ok(signal | Signal(1 << (c - b'a')))
+            }
+            _ => Err(ParseError {}),
+        })
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for wire in self.wires() {
+            write!(f, "{}", (b'a' + wire as u8) as char)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Digit {
+    Zero,
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+}
+
+impl Digit {
+    const ALL: [Digit; 10] = [
+        Digit::Zero,
+        Digit::One,
+        Digit::Two,
+        Digit::Three,
+        Digit::Four,
+        Digit::Five,
+        Digit::Six,
+        Digit::Seven,
+        Digit::Eight,
+        Digit::Nine,
+    ];
+
+    /// Digit that is the only one with `len` lit segments
+    fn with_unique_len(len: u32) -> Option<Digit> {
+        match len {
+            2 => Some(Digit::One),
+            3 => Some(Digit::Seven),
+            4 => Some(Digit::Four),
+            7 => Some(Digit::Eight),
+            _ => None,
         }
     }
+
+    fn segments(self) -> Signal {
+        Signal::try_from(DIGITS[self as usize]).unwrap()
+    }
 }
 
-type Output = [Candidate; 4];
-type Input = [Candidate; 10];
+type Output = [Signal; 4];
+type Input = [Signal; 10];
 
 #[derive(Debug, Clone)]
 struct ParseError;
@@ -42,22 +130,38 @@ impl fmt::Display for ParseError {
 
 impl Error for ParseError {}
 
-fn parse_signal(signal: &[u8]) -> Candidate {
-    match signal.len() {
-        2 => Candidate::One([signal[0], signal[1]]),
-        4 => Candidate::Four([signal[0], signal[1], signal[2], signal[3]]),
-        3 => Candidate::Seven([signal[0], signal[1], signal[2]]),
-        7 => Candidate::Eight([
-            signal[0], signal[1], signal[2], signal[3], signal[4], signal[5], signal[6],
-        ]),
-        5 => Candidate::UnknownFive([signal[0], signal[1], signal[2], signal[3], signal[4]]),
-        6 => Candidate::UnknownSix([
-            signal[0], signal[1], signal[2], signal[3], signal[4], signal[5],
-        ]),
-        _ => panic!("impossible"),
+#[derive(Debug, Clone, PartialEq)]
+enum DecodeError {
+    /// No signal for a digit with a unique number of segments
+    Missing(Digit),
+    /// Wire frequencies do not single out the given segment
+    Ambiguous(char),
+    /// Decoded segments do not form a digit, or one that another signal already formed
+    Inconsistent(Signal),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Missing(digit) => write!(f, "No signal for digit {:?}", digit),
+            DecodeError::Ambiguous(segment) => write!(f, "Cannot determine segment {}", segment),
+            DecodeError::Inconsistent(segments) => {
+                write!(f, "Segments {} do not form a digit", segments)
+            }
+        }
     }
 }
 
+impl Error for DecodeError {}
+
+/// Lit segments of the digits zero to nine
+const DIGITS: [&str; 10] = [
+    "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg",
+];
+
+/// Segment index for each wire `a` to `g`
+type Mapping = [usize; 7];
+
 fn parse_line(line: &str) -> Result<(Input, Output), ParseError> {
     let mut split = line.split('|');
     let mut left = split
@@ -68,15 +172,15 @@ fn parse_line(line: &str) -> Result<(Input, Output), ParseError> {
         .next()
         .ok_or_else(|| ParseError {})?
         .split_whitespace();
-    let mut input = [Candidate::Invalid; 10];
-    let mut output = [Candidate::Invalid; 4];
+    let mut input = [Signal::default(); 10];
+    let mut output = [Signal::default(); 4];
 
-    for candidate in input.iter_mut() {
-        *candidate = parse_signal(left.next().ok_or_else(|| ParseError {})?.as_bytes());
+    for signal in input.iter_mut() {
+        *signal = left.next().ok_or_else(|| ParseError {})?.try_into()?;
     }
 
-    for candidate in output.iter_mut() {
-        *candidate = parse_signal(right.next().ok_or_else(|| ParseError {})?.as_bytes());
+    for signal in output.iter_mut() {
+        *signal = right.next().ok_or_else(|| ParseError {})?.try_into()?;
     }
 
     Ok((input, output))
@@ -95,51 +199,150 @@ fn part_one(parsed: &[(Input, Output)]) -> usize {
         .map(|(_, output)| {
             output
                 .iter()
-                .filter(|c| {
-                    !matches!(
-                        c,
-                        Candidate::One(_)
-                            | Candidate::Four(_)
-                            | Candidate::Seven(_)
-                            | Candidate::Eight(_)
-                    )
-                })
+                .filter(|s| Digit::with_unique_len(s.len()).is_some())
                 .count()
         })
         .sum::<usize>()
 }
 
-fn decode(input: &Input) {
-    let one = input
-        .iter()
-        .find(|c| matches!(c, Candidate::One(_)))
-        .unwrap();
-    let seven = input
-        .iter()
-        .find(|c| matches!(c, Candidate::Seven(_)))
-        .unwrap();
+/// Derive the wire to segment mapping.
+///
+/// Across all ten digits the segments `b`, `e` and `f` are lit a unique number of times, `a` and
+/// `c` as well as `d` and `g` share their count and are told apart by one, four and seven.
+fn decode(input: &Input) -> Result<Mapping, DecodeError> {
+    let find = |digit: Digit| {
+        input
+            .iter()
+            .copied()
+            .find(|s| Digit::with_unique_len(s.len()) == Some(digit))
+            .ok_or(DecodeError::Missing(digit))
+    };
 
-    let a = match (one, seven) {
-        (Candidate::One(one), Candidate::Seven(seven)) => {
-            seven.iter().filter(|c| !one.contains(c)).next().unwrap()
-        }
-        _ => panic!("nono"),
+    let one = find(Digit::One)?;
+    let four = find(Digit::Four)?;
+    let seven = find(Digit::Seven)?;
+
+    let with_count = |count: usize| {
+        Signal(
+            (0..7)
+                .filter(|&wire| input.iter().filter(|s| s.contains(wire)).count() == count)
+                .fold(0, |set, wire| set | 1 << wire),
+        )
     };
 
-    let mut all = input
+    let candidates = [
+        with_count(8) & (seven - one),
+        with_count(6),
+        with_count(8) & one,
+        with_count(7) & four,
+        with_count(4),
+        with_count(9),
+        with_count(7) - four,
+    ];
+
+    let mut mapping = [0; 7];
+
+    for (segment, candidate) in candidates.iter().enumerate() {
+        match candidate.wires().collect::<Vec<_>>()[..] {
+            [wire] => mapping[wire] = segment,
+            _ => return Err(DecodeError::Ambiguous((b'a' + segment as u8) as char)),
+        }
+    }
+
+    // Frequencies only look at wire counts, the signals must still map onto the ten digits
+    let mut seen = [false; 10];
+
+    for &signal in input {
+        let digit = decode_digit(signal, &mapping)?;
+
+        if std::mem::replace(&mut seen[digit as usize], true) {
+            return Err(DecodeError::Inconsistent(digit.segments()));
+        }
+    }
+
+    Ok(mapping)
+}
+
+fn decode_digit(signal: Signal, mapping: &Mapping) -> Result<Digit, DecodeError> {
+    let segments = signal.wires().fold(Signal::default(), |set, wire| {
+        set | Signal(1 << mapping[wire])
+    });
+
+    Digit::ALL
         .iter()
-        .map(|i| i.data().iter())
-        .flatten()
-        .collect::<Vec<_>>();
-    all.sort();
+        .copied()
+        .find(|digit| digit.segments() == segments)
+        .ok_or(DecodeError::Inconsistent(segments))
+}
+
+fn decode_output(input: &Input, output: &Output) -> Result<usize, DecodeError> {
+    let mapping = decode(input)?;
 
-    println!("{:?}", all);
+    output.iter().try_fold(0, |value, &signal| {
+        Ok(value * 10 + decode_digit(signal, &mapping)? as usize)
+    })
+}
+
+fn part_two(parsed: &[(Input, Output)]) -> Result<usize, DecodeError> {
+    parsed
+        .iter()
+        .map(|(input, output)| decode_output(input, output))
+        .sum()
+}
+
+/// Decode the output of each line with the constraint solver for an arbitrary display
+fn solve_display<B: BufRead>(
+    display: &Display,
+    lines: &mut Lines<B>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut result = vec![];
+
+    for line in lines {
+        let line = line?;
+        let (patterns, output) = line.split_once('|').ok_or(ParseError {})?;
+        let output = output.split_whitespace().collect::<Vec<_>>();
+        let patterns = patterns
+            .split_whitespace()
+            .chain(output.iter().copied())
+            .collect::<Vec<_>>();
+
+        let wiring = display.solve(&patterns)?;
+
+        result.push(
+            output
+                .iter()
+                .map(|pattern| display.decode(&wiring, pattern))
+                .collect::<Result<String, _>>()?,
+        );
+    }
+
+    Ok(result)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reader = BufReader::new(File::open("input")?);
+    let mut args = std::env::args().skip(1);
+
+    if let Some(arg) = args.next() {
+        let display = match arg.as_str() {
+            "--solver" => Display::seven_segment(),
+            "--display" => {
+                let path = args.next().ok_or("--display requires a path")?;
+                std::fs::read_to_string(path)?.parse()?
+            }
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        };
+
+        for output in solve_display(&display, &mut reader.lines())? {
+            println!("{}", output);
+        }
+
+        return Ok(());
+    }
+
     let lines = parse_lines(&mut reader.lines())?;
     println!("{}", part_one(&lines));
+    println!("{}", part_two(&lines)?);
     Ok(())
 }
 
@@ -155,8 +358,13 @@ mod tests {
 fdgacbe cefdb cefbgd gcbe",
         )?;
 
-        assert!(matches!(output[0], Candidate::Eight(_)));
-        assert!(matches!(output[3], Candidate::Four(_)));
+        assert_eq!(Digit::with_unique_len(output[0].len()), Some(Digit::Eight));
+        assert_eq!(Digit::with_unique_len(output[3].len()), Some(Digit::Four));
+        assert_eq!(output[1], Signal::try_from("bcdef")?);
+        assert_eq!(output[1] & output[3], Signal::try_from("bce")?);
+
+        assert!(Signal::try_from("abh").is_err());
+        assert!(Signal::try_from("aba").is_err());
         Ok(())
     }
 
@@ -177,16 +385,32 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
 
         let lines = parse_lines(&mut cursor.lines())?;
         assert_eq!(part_one(&lines), 26);
+        assert_eq!(part_two(&lines)?, 61229);
 
         Ok(())
     }
 
     #[test]
     fn test_decode() -> Result<(), Box<dyn std::error::Error>> {
-        let (input, _output) = parse_line(
+        let (input, output) = parse_line(
             "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
         )?;
-        decode(&input);
+        assert_eq!(decode(&input)?, [2, 5, 6, 0, 1, 3, 4]);
+        assert_eq!(decode_output(&input, &output)?, 5353);
+
+        // Two identical signals for eight instead of a zero leave `d` and `g` indistinguishable
+        let (input, _) = parse_line(
+            "acedgfb cdfbe gcdfa fbcad dab acedgfb cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        )?;
+        assert!(decode(&input).is_err());
+
+        // Wire counts match the digits, but `abcdg` and `adefg` are no digits
+        let (input, output) =
+            parse_line("abcefg cf abcdg acdfg bcdf adefg abdefg acf abcdefg abcdfg | cf cf cf cf")?;
+        assert!(matches!(
+            decode_output(&input, &output),
+            Err(DecodeError::Inconsistent(_))
+        ));
 
         Ok(())
     }
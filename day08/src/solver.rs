@@ -0,0 +1,271 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolveError {
+    /// Character is not part of the display alphabet
+    UnknownWire(char),
+    /// Display definition is malformed
+    InvalidDisplay(String),
+    /// No wiring explains all patterns
+    Inconsistent,
+    /// More than one wiring explains all patterns
+    Ambiguous,
+    /// Pattern does not correspond to any symbol
+    UnknownPattern(String),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::UnknownWire(c) => write!(f, "Unknown wire {}", c),
+            SolveError::InvalidDisplay(line) => write!(f, "Invalid display definition {:?}", line),
+            SolveError::Inconsistent => write!(f, "No wiring matches the patterns"),
+            SolveError::Ambiguous => write!(f, "More than one wiring matches the patterns"),
+            SolveError::UnknownPattern(p) => write!(f, "Pattern {} is not a symbol", p),
+        }
+    }
+}
+
+impl Error for SolveError {}
+
+/// Display with named segments and the set of lit segments for each symbol
+#[derive(Debug)]
+pub struct Display {
+    alphabet: Vec<char>,
+    symbols: Vec<(char, u32)>,
+}
+
+impl Display {
+    pub fn new(alphabet: &str, table: &[(char, &str)]) -> Result<Self, SolveError> {
+        let alphabet = alphabet.chars().collect::<Vec<_>>();
+
+        if alphabet.is_empty() || alphabet.len() > 32 {
+            return Err(SolveError::InvalidDisplay(alphabet.iter().collect()));
+        }
+
+        let mut display = Self {
+            alphabet,
+            symbols: vec![],
+        };
+
+        for (symbol, segments) in table {
+            let set = display.set(segments)?;
+            display.symbols.push((*symbol, set));
+        }
+
+        Ok(display)
+    }
+
+    pub fn seven_segment() -> Self {
+        let table = crate::DIGITS
+            .iter()
+            .enumerate()
+            .map(|(digit, segments)| ((b'0' + digit as u8) as char, *segments))
+            .collect::<Vec<_>>();
+
+        Self::new("abcdefg", &table).unwrap()
+    }
+
+    /// Bit set of the wires in `pattern`
+    fn set(&self, pattern: &str) -> Result<u32, SolveError> {
+        pattern.chars().try_fold(0, |set, c| {
+            let index = self
+                .alphabet
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(SolveError::UnknownWire(c))?;
+            Ok(set | 1 << index)
+        })
+    }
+
+    /// Narrow down the segments each wire can drive until nothing changes, returns `false` if a
+    /// wire is left without any segment
+    fn propagate(&self, patterns: &[u32], domains: &mut [u32]) -> bool {
+        loop {
+            let before = domains.to_vec();
+
+            for &pattern in patterns {
+                let candidates =
+                    self.symbols
+                        .iter()
+                        .map(|&(_, segments)| segments)
+                        .filter(|segments| {
+                            segments.count_ones() == pattern.count_ones()
+                                && (0..domains.len())
+                                    .filter(|wire| pattern & 1 << wire != 0)
+                                    .all(|wire| domains[wire] & segments != 0)
+                        });
+
+                let (union, intersection) = candidates.fold((0, u32::MAX), |(u, i), segments| {
+                    (u | segments, i & segments)
+                });
+
+                // Wires of the pattern drive one of the candidate segments, whereas segments
+                // common to all candidates cannot be driven by any other wire
+                for (wire, domain) in domains.iter_mut().enumerate() {
+                    if pattern & 1 << wire != 0 {
+                        *domain &= union;
+                    } else {
+                        *domain &= !intersection;
+                    }
+                }
+            }
+
+            for wire in 0..domains.len() {
+                if domains[wire].count_ones() == 1 {
+                    let segment = domains[wire];
+
+                    for (other, domain) in domains.iter_mut().enumerate() {
+                        if other != wire {
+                            *domain &= !segment;
+                        }
+                    }
+                }
+            }
+
+            if domains.contains(&0) {
+                return false;
+            }
+
+            if domains == before {
+                return true;
+            }
+        }
+    }
+
+    fn search(&self, patterns: &[u32], mut domains: Vec<u32>, solutions: &mut Vec<Vec<usize>>) {
+        if solutions.len() > 1 || !self.propagate(patterns, &mut domains) {
+            return;
+        }
+
+        let undecided = (0..domains.len())
+            .filter(|&wire| domains[wire].count_ones() > 1)
+            .min_by_key(|&wire| domains[wire].count_ones());
+
+        match undecided {
+            Some(wire) => {
+                for segment in 0..self.alphabet.len() {
+                    if domains[wire] & 1 << segment != 0 {
+                        let mut domains = domains.clone();
+                        domains[wire] = 1 << segment;
+                        self.search(patterns, domains, solutions);
+                    }
+                }
+            }
+            None => {
+                let wiring = domains
+                    .iter()
+                    .map(|domain| domain.trailing_zeros() as usize)
+                    .collect::<Vec<_>>();
+
+                if patterns
+                    .iter()
+                    .all(|&pattern| self.symbol(&wiring, pattern).is_some())
+                {
+                    solutions.push(wiring);
+                }
+            }
+        }
+    }
+
+    fn symbol(&self, wiring: &[usize], pattern: u32) -> Option<char> {
+        let segments = wiring
+            .iter()
+            .enumerate()
+            .filter(|(wire, _)| pattern & 1 << wire != 0)
+            .fold(0, |set, (_, segment)| set | 1 << segment);
+
+        self.symbols
+            .iter()
+            .find(|&&(_, s)| s == segments)
+            .map(|&(symbol, _)| symbol)
+    }
+
+    /// Find the unique wiring, mapping each wire index to a segment index, that turns every
+    /// pattern into a symbol
+    pub fn solve(&self, patterns: &[&str]) -> Result<Vec<usize>, SolveError> {
+        let patterns = patterns
+            .iter()
+            .map(|p| self.set(p))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let full = u32::MAX >> (32 - self.alphabet.len());
+        let mut solutions = vec![];
+
+        self.search(&patterns, vec![full; self.alphabet.len()], &mut solutions);
+
+        match solutions.len() {
+            0 => Err(SolveError::Inconsistent),
+            1 => Ok(solutions.remove(0)),
+            _ => Err(SolveError::Ambiguous),
+        }
+    }
+
+    pub fn decode(&self, wiring: &[usize], pattern: &str) -> Result<char, SolveError> {
+        self.symbol(wiring, self.set(pattern)?)
+            .ok_or_else(|| SolveError::UnknownPattern(pattern.to_string()))
+    }
+}
+
+impl FromStr for Display {
+    type Err = SolveError;
+
+    /// Parse the alphabet on the first line followed by one `symbol segments` line per symbol
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let alphabet = lines
+            .next()
+            .ok_or_else(|| SolveError::InvalidDisplay(String::new()))?;
+
+        let table = lines
+            .map(|line| {
+                let mut split = line.split_whitespace();
+                let mut symbol = split.next().unwrap_or_default().chars();
+
+                match (symbol.next(), symbol.next(), split.next(), split.next()) {
+                    (Some(symbol), None, Some(segments), None) => Ok((symbol, segments)),
+                    _ => Err(SolveError::InvalidDisplay(line.to_string())),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(alphabet, &table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() -> Result<(), Box<dyn Error>> {
+        let display = Display::seven_segment();
+        let patterns = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab";
+        let wiring = display.solve(&patterns.split_whitespace().collect::<Vec<_>>())?;
+        assert_eq!(wiring, vec![2, 5, 6, 0, 1, 3, 4]);
+
+        let output = "cdfeb fcadb cdfeb cdbaf"
+            .split_whitespace()
+            .map(|p| display.decode(&wiring, p))
+            .collect::<Result<String, _>>()?;
+        assert_eq!(output, "5353");
+
+        // Lengths alone identify every symbol of this display
+        let bars: Display = "wxyz\n1 w\n2 wx\n3 wxy\n4 wxyz".parse()?;
+        let wiring = bars.solve(&["y", "zy", "zyx", "wxyz"])?;
+        assert_eq!(wiring, vec![3, 2, 0, 1]);
+        assert_eq!(bars.decode(&wiring, "yz")?, '2');
+
+        let mirrored: Display = "abcd\n1 ab\n2 cd".parse()?;
+        assert_eq!(mirrored.solve(&["ab", "cd"]), Err(SolveError::Ambiguous));
+        assert_eq!(bars.solve(&["wx", "yz"]), Err(SolveError::Inconsistent));
+        assert_eq!(bars.solve(&["q"]), Err(SolveError::UnknownWire('q')));
+        Ok(())
+    }
+}
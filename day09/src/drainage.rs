@@ -0,0 +1,398 @@
+use crate::{Connectivity, Map, Rules};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Water collected in a depression
+pub struct Lake {
+    /// Points draining into the lake, as indices in reading order
+    pub cells: Vec<usize>,
+    pub volume: u64,
+    /// Level at which water starts to spill over
+    pub pour: u32,
+    /// Lake receiving the spilled water, `None` if it runs off the map
+    pub target: Option<usize>,
+    capacity: u64,
+    merged_into: Option<usize>,
+}
+
+/// Rain falling on a height map, collecting in depressions and spilling from full lakes into
+/// their neighbors or off the map.
+///
+/// Every point drains along the steepest descent into a local minimum, each minimum starts out
+/// as its own lake. Lakes spilling into each other once full are merged into a single one. Water
+/// flows between the neighbors given by the connectivity of the rules.
+pub struct Drainage<'a> {
+    map: &'a Map,
+    connectivity: Connectivity,
+    owner: Vec<usize>,
+    lakes: Vec<Lake>,
+    /// Water that left the map
+    pub drained: u64,
+}
+
+impl<'a> Drainage<'a> {
+    pub fn new(map: &'a Map, rules: &Rules) -> Self {
+        let width = map.width as usize;
+        let num_cells = (map.width * map.height) as usize;
+        let height = |i: usize| map.points[i / width][i % width];
+
+        let next = (0..num_cells)
+            .map(|i| {
+                map.neighbors(i % width, i / width, rules.connectivity.offsets())
+                    .map(|(x, y)| y * width + x)
+                    .filter(|&n| height(n) < height(i))
+                    .min_by_key(|&n| height(n))
+                    .unwrap_or(i)
+            })
+            .collect::<Vec<_>>();
+
+        let mut owner = vec![usize::MAX; num_cells];
+        let mut lakes: Vec<Lake> = vec![];
+
+        for i in 0..num_cells {
+            let mut path = vec![i];
+
+            while owner[*path.last().unwrap()] == usize::MAX {
+                let last = *path.last().unwrap();
+
+                if next[last] == last {
+                    owner[last] = lakes.len();
+                    lakes.push(Lake {
+                        cells: vec![],
+                        volume: 0,
+                        pour: 0,
+                        target: None,
+                        capacity: 0,
+                        merged_into: None,
+                    });
+                    break;
+                }
+
+                path.push(next[last]);
+            }
+
+            let lake = owner[*path.last().unwrap()];
+
+            for cell in path {
+                if owner[cell] == usize::MAX || owner[cell] == lake {
+                    owner[cell] = lake;
+                }
+            }
+        }
+
+        for (i, &lake) in owner.iter().enumerate() {
+            lakes[lake].cells.push(i);
+        }
+
+        let mut drainage = Self {
+            map,
+            connectivity: rules.connectivity,
+            owner,
+            lakes,
+            drained: 0,
+        };
+
+        for id in 0..drainage.lakes.len() {
+            drainage.update(id);
+        }
+
+        drainage
+    }
+
+    fn height(&self, i: usize) -> u32 {
+        let width = self.map.width as usize;
+        self.map.points[i / width][i % width]
+    }
+
+    /// Find the lowest point on the lake boundary and how much water fits below it
+    fn update(&mut self, id: usize) {
+        let width = self.map.width as usize;
+        let (max_x, max_y) = (width - 1, self.map.height as usize - 1);
+        let mut best: Option<(u32, Option<usize>)> = None;
+
+        for &i in &self.lakes[id].cells {
+            let (x, y) = (i % width, i / width);
+
+            if x == 0 || y == 0 || x == max_x || y == max_y {
+                let candidate = (self.height(i), None);
+                best = Some(best.map_or(candidate, |best| best.min(candidate)));
+            }
+
+            for (x, y) in self.map.neighbors(x, y, self.connectivity.offsets()) {
+                let n = y * width + x;
+
+                if self.owner[n] != id {
+                    let candidate = (self.height(i).max(self.height(n)), Some(self.owner[n]));
+                    best = Some(best.map_or(candidate, |best| best.min(candidate)));
+                }
+            }
+        }
+
+        let (pour, target) = best.unwrap_or((u32::MAX, None));
+        let capacity = self.lakes[id]
+            .cells
+            .iter()
+            .map(|&i| pour.saturating_sub(self.height(i)) as u64)
+            .sum();
+
+        let lake = &mut self.lakes[id];
+        lake.pour = pour;
+        lake.target = target;
+        lake.capacity = capacity;
+    }
+
+    /// Follow merges to the lake that currently holds the water of `id`
+    fn resolve(&self, mut id: usize) -> usize {
+        while let Some(merged) = self.lakes[id].merged_into {
+            id = merged;
+        }
+
+        id
+    }
+
+    fn merge(&mut self, ids: &[usize]) -> usize {
+        let merged = self.lakes.len();
+        let mut cells = vec![];
+        let mut volume = 0;
+
+        for &id in ids {
+            cells.extend(self.lakes[id].cells.iter().copied());
+            volume += self.lakes[id].volume;
+            self.lakes[id].merged_into = Some(merged);
+        }
+
+        for &cell in &cells {
+            self.owner[cell] = merged;
+        }
+
+        cells.sort_unstable();
+        self.lakes.push(Lake {
+            cells,
+            volume,
+            pour: 0,
+            target: None,
+            capacity: 0,
+            merged_into: None,
+        });
+        self.update(merged);
+        merged
+    }
+
+    /// Pass water exceeding the capacity of a lake downstream until it reaches a lake with room
+    /// left or the map border, merging lakes that spill into each other in a cycle
+    fn settle(&mut self) {
+        while let Some(id) = (0..self.lakes.len()).find(|&id| {
+            let lake = &self.lakes[id];
+            lake.merged_into.is_none() && lake.volume > lake.capacity
+        }) {
+            let excess = self.lakes[id].volume - self.lakes[id].capacity;
+            self.lakes[id].volume = self.lakes[id].capacity;
+
+            let mut path = vec![id];
+
+            loop {
+                let current = *path.last().unwrap();
+
+                match self.lakes[current].target.map(|t| self.resolve(t)) {
+                    None => {
+                        self.drained += excess;
+                        break;
+                    }
+                    Some(target) if self.lakes[target].volume < self.lakes[target].capacity => {
+                        self.lakes[target].volume += excess;
+                        break;
+                    }
+                    Some(target) => match path.iter().position(|&p| p == target) {
+                        Some(start) => {
+                            let merged = self.merge(&path[start..]);
+                            self.lakes[merged].volume += excess;
+                            break;
+                        }
+                        None => path.push(target),
+                    },
+                }
+            }
+        }
+    }
+
+    /// Let `amount` units of water fall on every point
+    pub fn rain(&mut self, amount: u64) {
+        for id in 0..self.lakes.len() {
+            if self.lakes[id].merged_into.is_none() {
+                self.lakes[id].volume += amount * self.lakes[id].cells.len() as u64;
+            }
+        }
+
+        self.settle();
+    }
+
+    /// Lakes that have not been merged into another one, with their ids
+    pub fn lakes(&self) -> impl Iterator<Item = (usize, &Lake)> {
+        self.lakes
+            .iter()
+            .enumerate()
+            .filter(|(_, lake)| lake.merged_into.is_none())
+    }
+
+    /// Spill graph as the pour level and receiving lake of each lake
+    pub fn spills(&self) -> Vec<(usize, u32, Option<usize>)> {
+        self.lakes()
+            .map(|(id, lake)| (id, lake.pour, lake.target.map(|t| self.resolve(t))))
+            .collect()
+    }
+
+    /// Lowest point of a lake
+    pub fn low_point(&self, id: usize) -> (usize, usize) {
+        let width = self.map.width as usize;
+        let i = *self.lakes[id]
+            .cells
+            .iter()
+            .min_by_key(|&&i| self.height(i))
+            .unwrap();
+
+        (i % width, i / width)
+    }
+
+    /// Water surface of a lake, or the height of its lowest point if it is dry
+    pub fn level(&self, id: usize) -> f64 {
+        let mut heights = self.lakes[id]
+            .cells
+            .iter()
+            .map(|&i| self.height(i) as f64)
+            .collect::<Vec<_>>();
+        heights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mut volume = self.lakes[id].volume as f64;
+        let mut level = heights[0];
+
+        // Raise the surface one height step at a time, covering one more point each time
+        for (covered, next) in heights.iter().enumerate().skip(1) {
+            let needed = (next - level) * covered as f64;
+
+            if needed >= volume {
+                return level + volume / covered as f64;
+            }
+
+            volume -= needed;
+            level = *next;
+        }
+
+        level + volume / heights.len() as f64
+    }
+}
+
+/// Water surface of every point after endless rain, the lowest possible maximum height on any
+/// path to the map border
+pub fn filled_levels(map: &Map, rules: &Rules) -> Vec<u32> {
+    let width = map.width as usize;
+    let (max_x, max_y) = (width - 1, map.height as usize - 1);
+    let mut levels = vec![u32::MAX; (map.width * map.height) as usize];
+    let mut queue = BinaryHeap::new();
+
+    for y in 0..=max_y {
+        for x in 0..=max_x {
+            if x == 0 || y == 0 || x == max_x || y == max_y {
+                levels[y * width + x] = map.points[y][x];
+                queue.push(Reverse((map.points[y][x], x, y)));
+            }
+        }
+    }
+
+    while let Some(Reverse((level, x, y))) = queue.pop() {
+        for (x, y) in map.neighbors(x, y, rules.connectivity.offsets()) {
+            if levels[y * width + x] == u32::MAX {
+                let level = level.max(map.points[y][x]);
+                levels[y * width + x] = level;
+                queue.push(Reverse((level, x, y)));
+            }
+        }
+    }
+
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Cursor};
+
+    #[test]
+    fn test_drainage() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new("9999999\n9101039\n9999959\n9999999");
+        let map = Map::new(&mut cursor.lines())?;
+        let rules = Rules::default();
+        let mut drainage = Drainage::new(&map, &rules);
+
+        // Both depressions spill into each other over the 1 between them
+        let spills = drainage.spills();
+        let west = drainage.owner[8];
+        let east = drainage.owner[11];
+        assert!(spills.contains(&(west, 1, Some(east))));
+        assert!(spills.contains(&(east, 1, Some(west))));
+
+        drainage.rain(1);
+        assert_eq!(drainage.level(west), 1.0);
+
+        for _ in 0..20 {
+            drainage.rain(1);
+        }
+
+        // Both lakes merged and are filled up to the surrounding nines
+        let filled = filled_levels(&map, &rules);
+        let merged = drainage.resolve(west);
+        assert_eq!(merged, drainage.resolve(east));
+        assert_eq!(drainage.level(merged), 9.0);
+        assert_eq!(filled[8], 9);
+        assert_eq!(drainage.low_point(merged), (2, 1));
+        assert!(drainage.drained > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_levels_match_priority_flood() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor =
+            Cursor::new("2199943210\n3987894921\n9856789892\n8767896789\n9899965678\n9999999999");
+        let map = Map::new(&mut cursor.lines())?;
+        let width = map.width as usize;
+
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let rules = Rules {
+                connectivity,
+                ..Rules::default()
+            };
+            let mut drainage = Drainage::new(&map, &rules);
+
+            for _ in 0..100 {
+                drainage.rain(1);
+            }
+
+            let filled = filled_levels(&map, &rules);
+
+            for (id, lake) in drainage.lakes() {
+                let level = drainage.level(id);
+
+                for &i in &lake.cells {
+                    let height = map.points[i / width][i % width] as f64;
+                    assert_eq!(level.max(height), filled[i] as f64);
+                }
+            }
+        }
+
+        // The pit only leaks over the diagonal to the corner
+        let cursor = Cursor::new("199\n909\n999");
+        let pit = Map::new(&mut cursor.lines())?;
+        let mut rules = Rules::default();
+        assert_eq!(filled_levels(&pit, &rules)[4], 9);
+
+        rules.connectivity = Connectivity::Eight;
+        assert_eq!(filled_levels(&pit, &rules)[4], 1);
+
+        let mut drainage = Drainage::new(&pit, &rules);
+        drainage.rain(1);
+        assert_eq!(drainage.level(drainage.owner[4]), 1.0);
+        assert!(drainage.drained > 0);
+
+        Ok(())
+    }
+}
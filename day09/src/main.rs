@@ -1,7 +1,12 @@
+mod drainage;
+mod render;
+
+use drainage::Drainage;
+use render::Render;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader, BufWriter, Lines};
 
 #[derive(Debug, Clone)]
 struct ParseError;
@@ -27,18 +32,53 @@ fn parse_line(line: &str) -> Result<Vec<u32>, ParseError> {
         .collect::<Result<Vec<_>, _>>()?)
 }
 
-enum Neighborhood {
-    Corner([(usize, usize); 2]),
-    Border([(usize, usize); 3]),
-    Inside([(usize, usize); 4]),
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Connectivity {
+    /// Horizontal and vertical neighbors
+    Four,
+    /// Diagonal neighbors as well
+    Eight,
 }
 
-impl Neighborhood {
-    fn points(&self) -> &[(usize, usize)] {
+impl Connectivity {
+    fn offsets(self) -> &'static [(isize, isize)] {
         match self {
-            Neighborhood::Corner(data) => data,
-            Neighborhood::Border(data) => data,
-            Neighborhood::Inside(data) => data,
+            Connectivity::Four => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Connectivity::Eight => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        }
+    }
+
+    /// Neighbors that come before a point in reading order
+    fn preceding(self) -> &'static [(isize, isize)] {
+        &self.offsets()[..self.offsets().len() / 2]
+    }
+}
+
+/// What makes up a basin and its low point
+#[derive(Clone, Debug)]
+struct Rules {
+    /// Points at least this high separate basins
+    wall: u32,
+    /// Merge connected points of equal height without a lower neighbor into a single low point
+    plateaus: bool,
+    connectivity: Connectivity,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            wall: 9,
+            plateaus: false,
+            connectivity: Connectivity::Four,
         }
     }
 }
@@ -60,94 +100,362 @@ impl Map {
         })
     }
 
-    fn neighborhood(&self, x: usize, y: usize) -> Neighborhood {
-        let max_x = (self.width - 1) as usize;
-        let max_y = (self.height - 1) as usize;
-
-        match (x, y) {
-            (0, 0) => Neighborhood::Corner([(0, 1), (1, 0)]),
-            (x, y) if (x, y) == (max_x, max_y) => Neighborhood::Corner([(x, y - 1), (x - 1, y)]),
-            (x, 0) if x == max_x => Neighborhood::Corner([(x - 1, 0), (x, 1)]),
-            (x, 0) => Neighborhood::Border([(x - 1, 0), (x, 1), (x + 1, 0)]),
-            (0, y) if y == max_y => Neighborhood::Corner([(0, y - 1), (1, y - 1)]),
-            (0, y) => Neighborhood::Border([(0, y - 1), (1, y), (0, y + 1)]),
-            (x, y) if y == max_y => Neighborhood::Border([(x - 1, y), (x + 1, y), (x, y - 1)]),
-            (x, y) if x == max_x => Neighborhood::Border([(x - 1, y), (x, y - 1), (x, y + 1)]),
-            (x, y) => Neighborhood::Inside([(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)]),
-        }
+    fn neighbors(
+        &self,
+        x: usize,
+        y: usize,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        offsets.iter().filter_map(move |(dx, dy)| {
+            let (x, y) = (x as isize + dx, y as isize + dy);
+
+            if x >= 0 && y >= 0 && x < self.width as isize && y < self.height as isize {
+                Some((x as usize, y as usize))
+            } else {
+                None
+            }
+        })
     }
 
-    fn is_low_point(&self, x: usize, y: usize) -> bool {
+    fn is_low_point(&self, x: usize, y: usize, connectivity: Connectivity) -> bool {
         let p = self.points[y][x];
 
-        self.neighborhood(x, y)
-            .points()
-            .iter()
-            .all(|(x, y)| p < self.points[*y][*x])
+        self.neighbors(x, y, connectivity.offsets())
+            .all(|(x, y)| p < self.points[y][x])
     }
 
-    fn low_points_and_heights(&self) -> Vec<(usize, usize, u32)> {
-        (0..self.width)
-            .flat_map(|x| {
-                (0..self.height).filter_map(move |y| {
-                    let (x, y) = (x as usize, y as usize);
+    /// Whether the connected region of equal height around a point has no lower neighbor, the
+    /// region is recorded in `visited`
+    fn is_low_plateau(
+        &self,
+        x: usize,
+        y: usize,
+        connectivity: Connectivity,
+        visited: &mut [bool],
+    ) -> bool {
+        let height = self.points[y][x];
+        let mut remaining = vec![(x, y)];
+        let mut low = true;
+
+        visited[y * self.width as usize + x] = true;
 
-                    if self.is_low_point(x, y) {
-                        Some((x, y, self.points[y][x]))
-                    } else {
-                        None
-                    }
+        while let Some((x, y)) = remaining.pop() {
+            for (x, y) in self.neighbors(x, y, connectivity.offsets()) {
+                let i = y * self.width as usize + x;
+
+                if self.points[y][x] < height {
+                    low = false;
+                } else if self.points[y][x] == height && !visited[i] {
+                    visited[i] = true;
+                    remaining.push((x, y));
+                }
+            }
+        }
+
+        low
+    }
+
+    /// Low points according to `rules`, a plateau is represented by its first point in reading
+    /// order and walls are never low points
+    fn low_points_and_heights(&self, rules: &Rules) -> Vec<(usize, usize, u32)> {
+        if !rules.plateaus {
+            return (0..self.width)
+                .flat_map(|x| {
+                    (0..self.height).filter_map(move |y| {
+                        let (x, y) = (x as usize, y as usize);
+
+                        if self.points[y][x] < rules.wall
+                            && self.is_low_point(x, y, rules.connectivity)
+                        {
+                            Some((x, y, self.points[y][x]))
+                        } else {
+                            None
+                        }
+                    })
                 })
-            })
-            .collect::<Vec<_>>()
+                .collect::<Vec<_>>();
+        }
+
+        let mut visited = vec![false; (self.width * self.height) as usize];
+        let mut result = vec![];
+
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                if !visited[y * self.width as usize + x]
+                    && self.points[y][x] < rules.wall
+                    && self.is_low_plateau(x, y, rules.connectivity, &mut visited)
+                {
+                    result.push((x, y, self.points[y][x]));
+                }
+            }
+        }
+
+        result
     }
 
-    fn basin_size(&self, x: usize, y: usize) -> usize {
-        let mut remaining = vec![(x, y)];
-        let mut marked: Vec<(usize, usize)> = vec![];
+    /// Label all basins in a single scan, merging labels of touching points with union-find
+    fn basins(&self, rules: &Rules) -> Basins {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut labels = vec![0u32; width * height];
+        let mut parent = vec![0u32];
 
-        while !remaining.is_empty() {
-            let mut next = vec![];
+        fn find(parent: &mut [u32], mut label: u32) -> u32 {
+            while parent[label as usize] != label {
+                parent[label as usize] = parent[parent[label as usize] as usize];
+                label = parent[label as usize];
+            }
+
+            label
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                if self.points[y][x] >= rules.wall {
+                    continue;
+                }
+
+                let mut label = 0;
+
+                for (x, y) in self.neighbors(x, y, rules.connectivity.preceding()) {
+                    let other = labels[y * width + x];
+
+                    if other == 0 {
+                        continue;
+                    }
 
-            for p in &remaining {
-                for (x, y) in self.neighborhood(p.0, p.1).points() {
-                    let (x, y) = (*x, *y);
-                    let height = self.points[y][x];
+                    let other = find(&mut parent, other);
 
-                    if height < 9 && !marked.contains(&(x, y)) {
-                        next.push((x, y));
-                        marked.push((x, y));
+                    if label == 0 {
+                        label = other;
+                    } else if label != other {
+                        parent[label.max(other) as usize] = label.min(other);
+                        label = label.min(other);
                     }
                 }
+
+                if label == 0 {
+                    label = parent.len() as u32;
+                    parent.push(label);
+                }
+
+                labels[y * width + x] = label;
             }
+        }
+
+        let mut ids = vec![u32::MAX; parent.len()];
+        let mut basins: Vec<Basin> = vec![];
+
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+
+                if labels[i] == 0 {
+                    continue;
+                }
 
-            remaining = next;
+                let root = find(&mut parent, labels[i]) as usize;
+
+                if ids[root] == u32::MAX {
+                    ids[root] = basins.len() as u32;
+                    basins.push(Basin {
+                        id: basins.len(),
+                        size: 0,
+                        low_point: (x, y),
+                        cells: vec![],
+                    });
+                }
+
+                let basin = &mut basins[ids[root] as usize];
+                let (low_x, low_y) = basin.low_point;
+                basin.size += 1;
+                basin.cells.push(i as u32);
+
+                if self.points[y][x] < self.points[low_y][low_x] {
+                    basin.low_point = (x, y);
+                }
+
+                labels[i] = ids[root] + 1;
+            }
         }
 
-        marked.iter().count()
+        Basins {
+            width,
+            labels,
+            basins,
+        }
     }
 }
 
-fn solve_part_one(map: &Map) -> u32 {
-    map.low_points_and_heights().iter().map(|p| p.2 + 1).sum()
+#[derive(Debug, PartialEq)]
+struct Basin {
+    id: usize,
+    size: usize,
+    /// Lowest point, the first in reading order if there are several
+    low_point: (usize, usize),
+    /// Indices of the cells in reading order
+    cells: Vec<u32>,
 }
 
-fn solve_part_two(map: &Map) -> usize {
-    let mut sizes = map
-        .low_points_and_heights()
+/// Basins and the basin label of every cell, with `0` marking walls and `id + 1` otherwise
+struct Basins {
+    width: usize,
+    labels: Vec<u32>,
+    basins: Vec<Basin>,
+}
+
+impl Basins {
+    fn basin_at(&self, x: usize, y: usize) -> Option<&Basin> {
+        match self.labels[y * self.width + x] {
+            0 => None,
+            label => Some(&self.basins[label as usize - 1]),
+        }
+    }
+
+    /// Cells of the basin in reading order
+    fn cells(&self, id: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.basins[id]
+            .cells
+            .iter()
+            .map(move |&i| (i as usize % self.width, i as usize / self.width))
+    }
+}
+
+fn solve_part_one(map: &Map, rules: &Rules) -> u32 {
+    map.low_points_and_heights(rules)
         .iter()
-        .map(|p| map.basin_size(p.0, p.1))
+        .map(|p| p.2 + 1)
+        .sum()
+}
+
+/// Product of the sizes of the three largest basins, `None` if there are fewer than three
+fn solve_part_two(basins: &Basins) -> Option<usize> {
+    let mut sizes = basins
+        .basins
+        .iter()
+        .map(|basin| basin.size)
         .collect::<Vec<_>>();
 
     sizes.sort_by(|a, b| b.cmp(a));
-    sizes[0] * sizes[1] * sizes[2]
+    sizes.get(..3).map(|largest| largest.iter().product())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reader = BufReader::new(File::open("input")?);
     let map = Map::new(&mut reader.lines())?;
-    println!("{}", solve_part_one(&map));
-    println!("{}", solve_part_two(&map));
+    let mut rules = Rules::default();
+    let mut list_basins = false;
+    let mut point = None;
+    let mut ppm = None;
+    let mut ansi = false;
+    let mut scale = 4;
+    let mut rain = None;
+    let mut spills = false;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wall" => rules.wall = args.next().ok_or("--wall requires a height")?.parse()?,
+            "--plateaus" => rules.plateaus = true,
+            "--eight" => rules.connectivity = Connectivity::Eight,
+            "--basins" => list_basins = true,
+            "--basin" => point = Some(args.next().ok_or("--basin requires a point x,y")?),
+            "--ppm" => ppm = Some(args.next().ok_or("--ppm requires a path")?),
+            "--ansi" => ansi = true,
+            "--scale" => scale = args.next().ok_or("--scale requires a factor")?.parse()?,
+            "--rain" => {
+                rain = Some(
+                    args.next()
+                        .ok_or("--rain requires a number of steps")?
+                        .parse()?,
+                )
+            }
+            "--spills" => spills = true,
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+
+    let basins = map.basins(&rules);
+
+    println!("{}", solve_part_one(&map, &rules));
+    match solve_part_two(&basins) {
+        Some(product) => println!("{}", product),
+        None => println!("fewer than three basins"),
+    }
+
+    if list_basins {
+        for basin in &basins.basins {
+            let (x, y) = basin.low_point;
+            println!("{} {} {},{}", basin.id, basin.size, x, y);
+        }
+    }
+
+    if let Some(point) = point {
+        let (x, y) = point.split_once(',').ok_or("Expected a point x,y")?;
+        let (x, y) = (x.parse()?, y.parse()?);
+
+        if x >= map.width as usize || y >= map.height as usize {
+            return Err(format!("{},{} is outside of the map", x, y).into());
+        }
+
+        let basin = basins.basin_at(x, y).ok_or("Point is part of a wall")?;
+        let cells = basins
+            .cells(basin.id)
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect::<Vec<_>>();
+
+        println!("{} {} {}", basin.id, basin.size, cells.join(" "));
+    }
+
+    if ppm.is_some() || ansi {
+        let render = Render::new(&map, &rules, &basins);
+
+        if let Some(path) = ppm {
+            render.write_ppm(scale, &mut BufWriter::new(File::create(path)?))?;
+        }
+
+        if ansi {
+            print!("{}", render.ansi());
+        }
+    }
+
+    if rain.is_some() || spills {
+        let mut drainage = Drainage::new(&map, &rules);
+
+        if spills {
+            for (id, pour, target) in drainage.spills() {
+                let target = target.map_or("edge".to_string(), |t| t.to_string());
+                println!("{} {} -> {}", id, pour, target);
+            }
+        }
+
+        if let Some(steps) = rain {
+            for _ in 0..steps {
+                drainage.rain(1);
+            }
+
+            for (id, lake) in drainage.lakes() {
+                let (x, y) = drainage.low_point(id);
+                println!(
+                    "{} {},{} {} {:.2}",
+                    id,
+                    x,
+                    y,
+                    lake.volume,
+                    drainage.level(id)
+                );
+            }
+
+            // Water the map holds once every lake is full, for comparison
+            let capacity = drainage::filled_levels(&map, &rules)
+                .iter()
+                .zip(map.points.iter().flatten())
+                .map(|(level, height)| (level - height) as u64)
+                .sum::<u64>();
+
+            println!("{} {}", drainage.drained, capacity);
+        }
+    }
+
     Ok(())
 }
 
@@ -169,19 +477,113 @@ mod tests {
         let map = Map::new(&mut cursor.clone().lines())?;
         assert_eq!(map.width, 10);
         assert_eq!(map.height, 5);
-        assert!(map.is_low_point(9, 0));
+        assert!(map.is_low_point(9, 0, Connectivity::Four));
 
-        let low_points = map.low_points_and_heights();
+        let low_points = map.low_points_and_heights(&Rules::default());
         assert_eq!(low_points.len(), 4);
 
-        assert_eq!(solve_part_one(&map), 15);
+        assert_eq!(solve_part_one(&map, &Rules::default()), 15);
+
+        let basins = map.basins(&Rules::default());
+        assert_eq!(basins.basins.len(), 4);
+        assert_eq!(basins.basin_at(0, 0).unwrap().size, 3);
+        assert_eq!(basins.basin_at(9, 0).unwrap().size, 9);
+        assert_eq!(basins.basin_at(2, 2).unwrap().size, 14);
+        assert_eq!(basins.basin_at(6, 4).unwrap().size, 9);
+        assert_eq!(basins.basin_at(2, 0), None);
+
+        let mut low_points = basins
+            .basins
+            .iter()
+            .map(|b| b.low_point)
+            .collect::<Vec<_>>();
+        low_points.sort_unstable();
+        assert_eq!(low_points, vec![(1, 0), (2, 2), (6, 4), (9, 0)]);
+
+        let cells = basins.cells(basins.basin_at(0, 0).unwrap().id);
+        assert_eq!(cells.collect::<Vec<_>>(), vec![(0, 0), (1, 0), (0, 1)]);
+
+        assert_eq!(solve_part_two(&basins), Some(1134));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rules() -> Result<(), Box<dyn std::error::Error>> {
+        let map = Map::new(&mut Cursor::new("115\n159\n599").lines())?;
+        let mut rules = Rules::default();
+        assert!(map.low_points_and_heights(&rules).is_empty());
+
+        rules.plateaus = true;
+        assert_eq!(map.low_points_and_heights(&rules), vec![(0, 0, 1)]);
+        assert_eq!(map.basins(&rules).basins[0].size, 6);
+
+        rules.wall = 5;
+        assert_eq!(map.basins(&rules).basins[0].size, 3);
+
+        rules.wall = 1;
+        assert!(map.low_points_and_heights(&rules).is_empty());
+        assert!(map.basins(&rules).basins.is_empty());
 
-        assert_eq!(map.basin_size(0, 0), 3);
-        assert_eq!(map.basin_size(9, 0), 9);
-        assert_eq!(map.basin_size(2, 2), 14);
-        assert_eq!(map.basin_size(6, 4), 9);
+        let map = Map::new(&mut Cursor::new("191\n919\n191").lines())?;
+        let mut rules = Rules::default();
+        assert_eq!(map.low_points_and_heights(&rules).len(), 5);
+        assert_eq!(map.basins(&rules).basins.len(), 5);
 
-        assert_eq!(solve_part_two(&map), 1134);
+        rules.wall = 0;
+        assert_eq!(solve_part_one(&map, &rules), 0);
+        rules.wall = 9;
+
+        rules.connectivity = Connectivity::Eight;
+        assert!(map.low_points_and_heights(&rules).is_empty());
+        assert_eq!(map.basins(&rules).basins.len(), 1);
+        assert_eq!(map.basins(&rules).basins[0].size, 5);
+        assert_eq!(solve_part_two(&map.basins(&rules)), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_basins_match_flood_fill() -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = 0x5bd1e995u64;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        // Mostly open terrain with long wiggly walls makes labels merge late
+        let input = (0..60)
+            .map(|_| {
+                (0..80)
+                    .map(|_| if random() % 3 == 0 { '9' } else { '1' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let map = Map::new(&mut Cursor::new(input).lines())?;
+        let basins = map.basins(&Rules::default());
+
+        for basin in &basins.basins {
+            let (x, y) = basin.low_point;
+            let mut remaining = vec![(x, y)];
+            let mut visited = vec![(x, y)];
+
+            while let Some((x, y)) = remaining.pop() {
+                for (x, y) in map.neighbors(x, y, Connectivity::Four.offsets()) {
+                    if map.points[y][x] < 9 && !visited.contains(&(x, y)) {
+                        visited.push((x, y));
+                        remaining.push((x, y));
+                    }
+                }
+            }
+
+            visited.sort_unstable_by_key(|&(x, y)| (y, x));
+            assert_eq!(basins.cells(basin.id).collect::<Vec<_>>(), visited);
+            assert_eq!(basin.size, visited.len());
+        }
 
         Ok(())
     }
@@ -0,0 +1,168 @@
+use crate::{Basins, Map, Rules};
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+const WALL: [u8; 3] = [40, 40, 40];
+const OUTLINE: [u8; 3] = [255, 255, 255];
+const LOW_POINT: [u8; 3] = [255, 0, 0];
+
+/// Distinct colour for each basin id by stepping around the hue circle by the golden angle
+fn palette(id: usize) -> [u8; 3] {
+    let hue = (id as f64 * 137.508) % 360.0;
+    let x = 1.0 - ((hue / 60.0) % 2.0 - 1.0).abs();
+
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+
+    [
+        (r * 200.0) as u8 + 55,
+        (g * 200.0) as u8 + 55,
+        (b * 200.0) as u8 + 55,
+    ]
+}
+
+/// Colours of a height map with basins, their low points and the three largest basins outlined
+pub struct Render<'a> {
+    map: &'a Map,
+    basins: &'a Basins,
+    /// Whether each point is a low point, in reading order
+    low_points: Vec<bool>,
+    outlined: Vec<usize>,
+}
+
+impl<'a> Render<'a> {
+    pub fn new(map: &'a Map, rules: &Rules, basins: &'a Basins) -> Self {
+        let mut low_points = vec![false; (map.width * map.height) as usize];
+
+        for (x, y, _) in map.low_points_and_heights(rules) {
+            low_points[y * map.width as usize + x] = true;
+        }
+
+        let mut outlined = basins.basins.iter().collect::<Vec<_>>();
+        outlined.sort_by_key(|basin| Reverse(basin.size));
+
+        let outlined = outlined.iter().take(3).map(|basin| basin.id).collect();
+
+        Self {
+            map,
+            basins,
+            low_points,
+            outlined,
+        }
+    }
+
+    /// Whether the point belongs to an outlined basin and touches a point outside of it
+    fn on_outline(&self, x: usize, y: usize) -> bool {
+        let id = match self.basins.basin_at(x, y) {
+            Some(basin) if self.outlined.contains(&basin.id) => basin.id,
+            _ => return false,
+        };
+
+        let (width, height) = (self.map.width as usize, self.map.height as usize);
+
+        x == 0
+            || y == 0
+            || x == width - 1
+            || y == height - 1
+            || [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                .iter()
+                .any(|&(x, y)| self.basins.basin_at(x, y).map(|b| b.id) != Some(id))
+    }
+
+    fn colour(&self, x: usize, y: usize) -> [u8; 3] {
+        if self.low_points[y * self.map.width as usize + x] {
+            return LOW_POINT;
+        }
+
+        if self.on_outline(x, y) {
+            return OUTLINE;
+        }
+
+        match self.basins.basin_at(x, y) {
+            // Higher points are drawn darker
+            Some(basin) => palette(basin.id)
+                .map(|c| (c as u32 * (12 - self.map.points[y][x].min(9)) / 12) as u8),
+            None => WALL,
+        }
+    }
+
+    /// Write a binary PPM with each point drawn as a `scale` by `scale` square
+    pub fn write_ppm<W: Write>(&self, scale: usize, out: &mut W) -> io::Result<()> {
+        let (width, height) = (self.map.width as usize, self.map.height as usize);
+
+        write!(out, "P6\n{} {}\n255\n", width * scale, height * scale)?;
+
+        for y in 0..height {
+            let row = (0..width)
+                .flat_map(|x| {
+                    let colour = self.colour(x, y);
+                    (0..scale).flat_map(move |_| colour)
+                })
+                .collect::<Vec<_>>();
+
+            for _ in 0..scale {
+                out.write_all(&row)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Heights as digits on a 24-bit background colour for terminals
+    pub fn ansi(&self) -> String {
+        let mut result = String::new();
+
+        for y in 0..self.map.height as usize {
+            for x in 0..self.map.width as usize {
+                let [r, g, b] = self.colour(x, y);
+                result.push_str(&format!(
+                    "\x1b[30;48;2;{};{};{}m{}",
+                    r, g, b, self.map.points[y][x]
+                ));
+            }
+
+            result.push_str("\x1b[0m\n");
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Cursor};
+
+    #[test]
+    fn test_render() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new("2199943210\n3987894921\n9856789892\n8767896789\n9899965678");
+        let map = Map::new(&mut cursor.lines())?;
+        let rules = Rules::default();
+        let basins = map.basins(&rules);
+        let render = Render::new(&map, &rules, &basins);
+
+        // Smallest basin in the top left is not outlined
+        assert_eq!(render.colour(1, 0), LOW_POINT);
+        assert_eq!(render.colour(2, 0), WALL);
+        assert_ne!(render.colour(0, 0), OUTLINE);
+        assert_eq!(render.colour(9, 1), OUTLINE);
+        assert_eq!(render.colour(8, 1), OUTLINE);
+        assert_ne!(render.colour(3, 2), OUTLINE);
+
+        let ansi = render.ansi();
+        assert_eq!(ansi.lines().count(), 5);
+        assert_eq!(ansi.matches("\x1b[30;48;2;255;0;0m").count(), 4);
+
+        let mut ppm = vec![];
+        render.write_ppm(2, &mut ppm)?;
+        assert!(ppm.starts_with(b"P6\n20 10\n255\n"));
+        assert_eq!(ppm.len(), b"P6\n20 10\n255\n".len() + 20 * 10 * 3);
+        Ok(())
+    }
+}
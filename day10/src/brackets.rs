@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BracketError {
+    /// Pair definition is malformed
+    InvalidPair(String),
+    /// Character is used by more than one pair
+    Duplicate(char),
+    /// Character is neither an opening nor a closing delimiter
+    UnknownCharacter(char),
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BracketError::InvalidPair(line) => write!(f, "Invalid bracket pair {:?}", line),
+            BracketError::Duplicate(c) => write!(f, "Delimiter {} is used more than once", c),
+            BracketError::UnknownCharacter(c) => write!(f, "Unknown character {:?}", c),
+        }
+    }
+}
+
+impl Error for BracketError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bracket {
+    pub open: char,
+    pub close: char,
+    /// Points for a line corrupted by this closing delimiter
+    pub corruption: usize,
+    /// Points for completing a line with this closing delimiter
+    pub completion: usize,
+}
+
+/// Delimiter pairs with their scores
+#[derive(Debug, Clone)]
+pub struct BracketSet {
+    pairs: Vec<Bracket>,
+}
+
+impl BracketSet {
+    pub fn new(pairs: Vec<Bracket>) -> Result<Self, BracketError> {
+        let mut seen = vec![];
+
+        for pair in &pairs {
+            for c in [pair.open, pair.close] {
+                if seen.contains(&c) {
+                    return Err(BracketError::Duplicate(c));
+                }
+
+                seen.push(c);
+            }
+        }
+
+        Ok(Self { pairs })
+    }
+
+    /// Pair opened by `c`
+    pub fn opened_by(&self, c: char) -> Option<&Bracket> {
+        self.pairs.iter().find(|pair| pair.open == c)
+    }
+
+    /// Pair closed by `c`
+    pub fn closed_by(&self, c: char) -> Option<&Bracket> {
+        self.pairs.iter().find(|pair| pair.close == c)
+    }
+}
+
+impl Default for BracketSet {
+    fn default() -> Self {
+        "( ) 3 1\n[ ] 57 2\n{ } 1197 3\n< > 25137 4"
+            .parse()
+            .unwrap()
+    }
+}
+
+impl FromStr for BracketSet {
+    type Err = BracketError;
+
+    /// Parse one `open close corruption completion` line per pair
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pairs = s
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let invalid = || BracketError::InvalidPair(line.to_string());
+                let fields = line.split_whitespace().collect::<Vec<_>>();
+
+                let single = |field: &str| {
+                    let mut chars = field.chars();
+
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Ok(c),
+                        _ => Err(invalid()),
+                    }
+                };
+
+                match fields[..] {
+                    [open, close, corruption, completion] => Ok(Bracket {
+                        open: single(open)?,
+                        close: single(close)?,
+                        corruption: corruption.parse().map_err(|_| invalid())?,
+                        completion: completion.parse().map_err(|_| invalid())?,
+                    }),
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bracket_set() -> Result<(), Box<dyn Error>> {
+        let set: BracketSet = "# quotes\n« » 10 1\n/ \\ 20 2".parse()?;
+        assert_eq!(set.opened_by('«').map(|pair| pair.close), Some('»'));
+        assert_eq!(set.closed_by('\\').map(|pair| pair.corruption), Some(20));
+        assert!(set.opened_by('(').is_none());
+
+        assert_eq!(
+            BracketSet::default().closed_by('>').map(|p| p.completion),
+            Some(4)
+        );
+        assert_eq!(
+            "( ) 1 1\n[ ( 2 2".parse::<BracketSet>().unwrap_err(),
+            BracketError::Duplicate('(')
+        );
+        assert_eq!(
+            "( ) 1".parse::<BracketSet>().unwrap_err(),
+            BracketError::InvalidPair("( ) 1".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use crate::brackets::{BracketError, BracketSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Closer does not match the innermost open delimiter
+    Mismatched {
+        found: char,
+        expected: char,
+        opener: usize,
+    },
+    /// Closer without any open delimiter
+    Unopened(char),
+    /// Opener still open at the end of the line
+    Unclosed { opener: usize, expected: char },
+}
+
+/// Problem at a column of a line, counted in characters from zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Diagnostic {
+    pub column: usize,
+    pub kind: Kind,
+}
+
+impl Diagnostic {
+    /// Closer causing the problem, if any
+    pub fn found(&self) -> Option<char> {
+        match self.kind {
+            Kind::Mismatched { found, .. } | Kind::Unopened(found) => Some(found),
+            Kind::Unclosed { .. } => None,
+        }
+    }
+
+    /// Column of the open delimiter involved, if any
+    pub fn opener(&self) -> Option<usize> {
+        match self.kind {
+            Kind::Mismatched { opener, .. } | Kind::Unclosed { opener, .. } => Some(opener),
+            Kind::Unopened(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Mismatched {
+                found,
+                expected,
+                opener,
+            } => write!(
+                f,
+                "expected {} to close column {}, found {}",
+                expected,
+                opener + 1,
+                found
+            ),
+            Kind::Unopened(found) => write!(f, "unexpected {} without open delimiter", found),
+            Kind::Unclosed { opener, expected } => {
+                write!(f, "expected {} to close column {}", expected, opener + 1)
+            }
+        }
+    }
+}
+
+/// Open delimiters with their columns, innermost last
+pub type Stack = Vec<(usize, char)>;
+
+/// Feed the character at `column` to the open delimiters, returns the problem if it is a closer
+/// that does not match the innermost open delimiter, leaving the stack unchanged in that case
+pub fn step(
+    set: &BracketSet,
+    stack: &mut Stack,
+    column: usize,
+    c: char,
+) -> Result<Option<Kind>, BracketError> {
+    if set.opened_by(c).is_some() {
+        stack.push((column, c));
+        return Ok(None);
+    }
+
+    let pair = set.closed_by(c).ok_or(BracketError::UnknownCharacter(c))?;
+
+    match stack.last() {
+        Some(&(_, open)) if open == pair.open => {
+            stack.pop();
+            Ok(None)
+        }
+        Some(&(opener, open)) => Ok(Some(Kind::Mismatched {
+            found: c,
+            expected: set.opened_by(open).unwrap().close,
+            opener,
+        })),
+        None => Ok(Some(Kind::Unopened(c))),
+    }
+}
+
+/// Scan a line for problems, stopping at the first one unless `recover` is set
+///
+/// Returns the problems along with the delimiters left open and their columns. To recover, a
+/// closer matching a delimiter further out closes the delimiters in between as if their closers
+/// were inserted, any other bad closer is skipped as if it were deleted.
+pub fn scan(
+    set: &BracketSet,
+    s: &str,
+    recover: bool,
+) -> Result<(Vec<Diagnostic>, Stack), BracketError> {
+    let mut stack: Stack = vec![];
+    let mut diagnostics = vec![];
+
+    for (column, c) in s.chars().enumerate() {
+        let kind = match step(set, &mut stack, column, c)? {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        diagnostics.push(Diagnostic { column, kind });
+
+        if !recover {
+            break;
+        }
+
+        let open = set.closed_by(c).unwrap().open;
+
+        if let Some(outer) = stack.iter().rposition(|&(_, o)| o == open) {
+            stack.truncate(outer);
+        }
+    }
+
+    Ok((diagnostics, stack))
+}
+
+/// Every problem of a line, recovering from errors and reporting unclosed delimiters at the end
+pub fn check(set: &BracketSet, s: &str) -> Result<Vec<Diagnostic>, BracketError> {
+    let (mut diagnostics, stack) = scan(set, s, true)?;
+    let end = s.chars().count();
+
+    diagnostics.extend(stack.iter().rev().map(|&(opener, open)| Diagnostic {
+        column: end,
+        kind: Kind::Unclosed {
+            opener,
+            expected: set.opened_by(open).unwrap().close,
+        },
+    }));
+
+    Ok(diagnostics)
+}
+
+/// Line followed by one caret underline per diagnostic, marking the related opener with `-`
+pub fn render(s: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut result = format!("{}\n", s);
+
+    for diagnostic in diagnostics {
+        let mut marks = vec![' '; diagnostic.column + 1];
+
+        if let Some(opener) = diagnostic.opener() {
+            marks[opener] = '-';
+        }
+
+        marks[diagnostic.column] = '^';
+        result.push_str(&format!(
+            "{} {}\n",
+            marks.iter().collect::<String>(),
+            diagnostic
+        ));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() -> Result<(), BracketError> {
+        let set = BracketSet::default();
+
+        let (diagnostics, _) = scan(&set, "{([(<{}[<>[]}>{[]{[(<()>", false)?;
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                column: 12,
+                kind: Kind::Mismatched {
+                    found: '}',
+                    expected: ']',
+                    opener: 7
+                }
+            }]
+        );
+
+        // Missing `]` is inserted, leaving the last `>` without an opener
+        let diagnostics = check(&set, "(<[>)>")?;
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].column, 3);
+        assert_eq!(diagnostics[1].column, 5);
+        assert_eq!(diagnostics[1].kind, Kind::Unopened('>'));
+
+        // Stray `]` is deleted and both `(` are left open
+        let unclosed = check(&set, "((]")?;
+        assert_eq!(unclosed.len(), 3);
+        assert_eq!(unclosed[0].opener(), Some(1));
+        assert_eq!(unclosed[1].column, 3);
+        assert_eq!(
+            unclosed[2].kind,
+            Kind::Unclosed {
+                opener: 0,
+                expected: ')'
+            }
+        );
+        assert_eq!(unclosed[2].found(), None);
+
+        assert_eq!(
+            render("(<[>)>", &diagnostics[..1]),
+            "(<[>)>\n  -^ expected ] to close column 3, found >\n"
+        );
+        assert!(check(&set, "<{}>")?.is_empty());
+        Ok(())
+    }
+}
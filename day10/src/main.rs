@@ -1,106 +1,62 @@
+mod brackets;
+mod diagnostic;
+mod repair;
+mod stream;
+
+use brackets::{BracketError, BracketSet};
+use diagnostic::Diagnostic;
+use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader, Lines, Read};
+use stream::Validator;
 
-enum Line {
-    Corrupt(char),
+pub enum Line {
+    Corrupt(Diagnostic),
     Incomplete(Vec<char>),
     Good,
 }
 
-fn closing(c: char) -> char {
-    match c {
-        '(' => ')',
-        '[' => ']',
-        '{' => '}',
-        '<' => '>',
-        _ => unreachable!(),
-    }
-}
+fn parse_line(set: &BracketSet, s: &str) -> Result<Line, BracketError> {
+    let (diagnostics, stack) = diagnostic::scan(set, s, false)?;
 
-fn opening(c: char) -> char {
-    match c {
-        ')' => '(',
-        ']' => '[',
-        '}' => '{',
-        '>' => '<',
-        _ => unreachable!(),
+    if let Some(&diagnostic) = diagnostics.first() {
+        Ok(Line::Corrupt(diagnostic))
+    } else if stack.is_empty() {
+        Ok(Line::Good)
+    } else {
+        Ok(Line::Incomplete(stack.iter().map(|&(_, c)| c).collect()))
     }
 }
 
-fn score_incomplete(c: char) -> usize {
-    match c {
-        ')' => 3,
-        ']' => 57,
-        '}' => 1197,
-        '>' => 25137,
-        _ => unreachable!(),
-    }
+fn parse_lines<B: BufRead>(
+    set: &BracketSet,
+    lines: &mut Lines<B>,
+) -> Result<Vec<Line>, Box<dyn Error>> {
+    lines
+        .map(|l| Ok(parse_line(set, &l?)?))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()
 }
 
-fn score_completion(c: char) -> usize {
-    match c {
-        ')' => 1,
-        ']' => 2,
-        '}' => 3,
-        '>' => 4,
-        _ => unreachable!(),
+fn score_corrupt(set: &BracketSet, line: &Line) -> usize {
+    match line {
+        Line::Corrupt(diagnostic) => diagnostic
+            .found()
+            .and_then(|c| set.closed_by(c))
+            .map_or(0, |pair| pair.corruption),
+        _ => 0,
     }
 }
 
-fn parse_line(s: &str) -> Line {
-    let mut stack = vec![];
-
-    for c in s.chars() {
-        match c {
-            '(' | '[' | '{' | '<' => stack.push(c),
-            ')' | ']' | '}' | '>' => {
-                let last = stack.last();
-
-                if last.is_none() {
-                    return Line::Corrupt(c);
-                }
-
-                let last = *last.unwrap();
-
-                if last != opening(c) {
-                    return Line::Corrupt(c);
-                }
-
-                stack.remove(stack.len() - 1);
-            }
-            _ => panic!(),
-        }
-    }
-
-    if stack.is_empty() {
-        Line::Good
-    } else {
-        Line::Incomplete(stack)
-    }
+fn solve_part_one(set: &BracketSet, lines: &Vec<Line>) -> usize {
+    lines.iter().map(|l| score_corrupt(set, l)).sum()
 }
 
-fn parse_lines<B: BufRead>(lines: &mut Lines<B>) -> Result<Vec<Line>, std::io::Error> {
-    lines
-        .map(|l| l.map(|l| parse_line(&l)))
-        .collect::<Result<Vec<_>, _>>()
-}
-
-fn solve_part_one(lines: &Vec<Line>) -> usize {
-    lines
-        .iter()
-        .map(|l| match l {
-            Line::Corrupt(c) => score_incomplete(*c),
-            _ => 0,
-        })
-        .sum()
-}
-
-fn score_line(line: &Line) -> usize {
+fn score_line(set: &BracketSet, line: &Line) -> usize {
     let points: Vec<usize> = match line {
         Line::Incomplete(stack) => stack
             .iter()
             .rev()
-            .map(|&c| score_completion(closing(c)))
+            .map(|&c| set.opened_by(c).map_or(0, |pair| pair.completion))
             .collect(),
         _ => unreachable!(),
     };
@@ -115,11 +71,11 @@ fn score_line(line: &Line) -> usize {
     total_score
 }
 
-fn solve_part_two(lines: &Vec<Line>) -> usize {
+fn solve_part_two(set: &BracketSet, lines: &Vec<Line>) -> usize {
     let mut scores = lines
         .iter()
         .filter_map(|l| match l {
-            Line::Incomplete(_) => Some(score_line(&l)),
+            Line::Incomplete(_) => Some(score_line(set, l)),
             _ => None,
         })
         .collect::<Vec<_>>();
@@ -129,10 +85,96 @@ fn solve_part_two(lines: &Vec<Line>) -> usize {
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut set = BracketSet::default();
+    let mut check = false;
+    let mut repair = false;
+    let mut stream = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--brackets" => {
+                let path = args.next().ok_or("--brackets requires a path")?;
+                set = std::fs::read_to_string(path)?.parse()?;
+            }
+            "--check" => check = true,
+            "--repair" => repair = true,
+            "--stream" => stream = Some(args.next().ok_or("--stream requires a path or -")?),
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+
+    if check {
+        let reader = BufReader::new(File::open("input")?);
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let diagnostics = diagnostic::check(&set, &line)?;
+
+            if !diagnostics.is_empty() {
+                println!("line {}:", number + 1);
+                print!("{}", diagnostic::render(&line, &diagnostics));
+            }
+        }
+
+        return Ok(());
+    }
+
+    if repair {
+        let reader = BufReader::new(File::open("input")?);
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if let Line::Corrupt(_) = parse_line(&set, &line)? {
+                let repair = repair::repair(&set, &line)?;
+                println!("{} {}", repair.cost, repair.line);
+            }
+        }
+
+        return Ok(());
+    }
+
+    if let Some(path) = stream {
+        let reader: Box<dyn Read> = match path.as_str() {
+            "-" => Box::new(std::io::stdin()),
+            _ => Box::new(File::open(path)?),
+        };
+
+        let mut validator = Validator::new(&set, reader);
+        let mut corruption = 0;
+        let mut completions = vec![];
+
+        for line in validator.by_ref() {
+            let line = line?;
+            corruption += score_corrupt(&set, &line);
+
+            if let Line::Incomplete(_) = line {
+                completions.push(score_line(&set, &line));
+            }
+        }
+
+        completions.sort_unstable();
+        println!("{}", corruption);
+        println!("{}", completions.get(completions.len() / 2).unwrap_or(&0));
+
+        let stats = validator.stats();
+        let mut opened = stats.opened.iter().collect::<Vec<_>>();
+        opened.sort();
+
+        println!("lines {} max depth {}", stats.lines, stats.max_depth);
+
+        for (c, count) in opened {
+            println!("{} {}", c, count);
+        }
+
+        return Ok(());
+    }
+
     let reader = BufReader::new(File::open("input")?);
-    let lines = parse_lines(&mut reader.lines())?;
-    println!("{}", solve_part_one(&lines));
-    println!("{}", solve_part_two(&lines));
+    let lines = parse_lines(&set, &mut reader.lines())?;
+    println!("{}", solve_part_one(&set, &lines));
+    println!("{}", solve_part_two(&set, &lines));
     Ok(())
 }
 
@@ -142,13 +184,24 @@ mod tests {
     use std::io::Cursor;
 
     #[test]
-    fn test_parse_line() {
-        assert!(matches!(parse_line("([])"), Line::Good));
-        assert!(matches!(parse_line("([]"), Line::Incomplete(_)));
-        assert!(matches!(parse_line("([)]"), Line::Corrupt(_)));
-
-        let line = parse_line("{([(<{}[<>[]}>{[]{[(<()>");
-        assert!(matches!(line, Line::Corrupt(c) if c == '}'));
+    fn test_parse_line() -> Result<(), BracketError> {
+        let set = BracketSet::default();
+        assert!(matches!(parse_line(&set, "([])")?, Line::Good));
+        assert!(matches!(parse_line(&set, "([]")?, Line::Incomplete(_)));
+        assert!(matches!(parse_line(&set, "([)]")?, Line::Corrupt(_)));
+
+        let line = parse_line(&set, "{([(<{}[<>[]}>{[]{[(<()>")?;
+        assert!(matches!(line, Line::Corrupt(d) if d.found() == Some('}') && d.column == 12));
+
+        assert!(matches!(
+            parse_line(&set, "(a)"),
+            Err(BracketError::UnknownCharacter('a'))
+        ));
+
+        let quotes: BracketSet = "« » 7 1".parse()?;
+        assert!(matches!(parse_line(&quotes, "««»")?, Line::Incomplete(_)));
+        assert!(matches!(parse_line(&quotes, "»")?, Line::Corrupt(d) if d.found() == Some('»')));
+        Ok(())
     }
 
     #[test]
@@ -166,9 +219,10 @@ mod tests {
 <{([{{}}[<[[[<>{}]]]>[]]"#,
         );
 
-        let lines = parse_lines(&mut cursor.lines())?;
-        assert_eq!(solve_part_one(&lines), 26397);
-        assert_eq!(solve_part_two(&lines), 288957);
+        let set = BracketSet::default();
+        let lines = parse_lines(&set, &mut cursor.lines())?;
+        assert_eq!(solve_part_one(&set, &lines), 26397);
+        assert_eq!(solve_part_two(&set, &lines), 288957);
 
         Ok(())
     }
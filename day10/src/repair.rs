@@ -0,0 +1,133 @@
+use crate::brackets::{BracketError, BracketSet};
+
+/// Balanced line closest to the original and the number of edits to get there
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    pub cost: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Choice {
+    /// Balanced already, nothing left
+    Empty,
+    /// Pair the first character with a newly inserted partner next to it
+    Insert,
+    /// Pair the first character with the one at the given index
+    Match(usize),
+}
+
+/// Find the fewest insertions, deletions and substitutions that balance `s`
+///
+/// Deleting a character costs as much as inserting its partner right next to it, so the repair
+/// only ever inserts or substitutes and keeps every character of the line in place.
+pub fn repair(set: &BracketSet, s: &str) -> Result<Repair, BracketError> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let n = chars.len();
+
+    for &c in &chars {
+        if set.opened_by(c).is_none() && set.closed_by(c).is_none() {
+            return Err(BracketError::UnknownCharacter(c));
+        }
+    }
+
+    let pair = |i: usize, k: usize| -> (usize, char, char) {
+        match (set.opened_by(chars[i]), set.closed_by(chars[k])) {
+            (Some(open), Some(close)) if open == close => (0, chars[i], chars[k]),
+            (Some(open), _) => (1, chars[i], open.close),
+            (None, Some(close)) => (1, close.open, chars[k]),
+            (None, None) => {
+                let open = set.closed_by(chars[i]).unwrap();
+                (2, open.open, open.close)
+            }
+        }
+    };
+
+    // Cheapest repair of chars[i..j] for every range
+    let mut cost = vec![vec![0; n + 1]; n + 1];
+    let mut choice = vec![vec![Choice::Empty; n + 1]; n + 1];
+
+    for len in 1..=n {
+        for i in 0..=n - len {
+            let j = i + len;
+            let mut best = (1 + cost[i + 1][j], Choice::Insert);
+
+            for k in i + 1..j {
+                let candidate = pair(i, k).0 + cost[i + 1][k] + cost[k + 1][j];
+
+                if candidate < best.0 {
+                    best = (candidate, Choice::Match(k));
+                }
+            }
+
+            cost[i][j] = best.0;
+            choice[i][j] = best.1;
+        }
+    }
+
+    let mut line = String::new();
+    let mut ranges = vec![(0, n, None)];
+
+    // Rebuild the repaired line from the choices, pushing closers to emit after their contents
+    while let Some((i, j, after)) = ranges.pop() {
+        match choice[i][j] {
+            Choice::Empty => {}
+            Choice::Insert => match set.opened_by(chars[i]) {
+                Some(open) => {
+                    line.push(chars[i]);
+                    line.push(open.close);
+                    ranges.push((i + 1, j, None));
+                }
+                None => {
+                    line.push(set.closed_by(chars[i]).unwrap().open);
+                    line.push(chars[i]);
+                    ranges.push((i + 1, j, None));
+                }
+            },
+            Choice::Match(k) => {
+                let (_, open, close) = pair(i, k);
+                line.push(open);
+                ranges.push((k + 1, j, None));
+                ranges.push((k, k, Some(close)));
+                ranges.push((i + 1, k, None));
+            }
+        }
+
+        if let Some(close) = after {
+            line.push(close);
+        }
+    }
+
+    Ok(Repair {
+        cost: cost[0][n],
+        line,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic;
+
+    #[test]
+    fn test_repair() -> Result<(), BracketError> {
+        let set = BracketSet::default();
+        let repaired = |s| repair(&set, s).map(|r| (r.cost, r.line));
+
+        assert_eq!(repaired("<{}>")?, (0, "<{}>".to_string()));
+        assert_eq!(repaired("(]")?, (1, "()".to_string()));
+        assert_eq!(repaired("(<)")?, (1, "(<>)".to_string()));
+        assert_eq!(repaired("")?, (0, String::new()));
+        assert!(repair(&set, "(x)").is_err());
+
+        for (line, cost) in [("([)]", 2), (")(", 2), ("{([(<{}[<>[]}>{[]{[(<()>", 5)] {
+            let repair = repair(&set, line)?;
+            assert_eq!(repair.cost, cost);
+
+            let (diagnostics, stack) = diagnostic::scan(&set, &repair.line, false)?;
+            assert!(diagnostics.is_empty() && stack.is_empty());
+        }
+
+        Ok(())
+    }
+}
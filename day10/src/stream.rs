@@ -0,0 +1,216 @@
+use crate::brackets::BracketSet;
+use crate::diagnostic::{self, Diagnostic, Stack};
+use crate::Line;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Nesting statistics over everything read so far
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    pub lines: usize,
+    pub max_depth: usize,
+    /// Number of delimiters opened per opening character
+    pub opened: HashMap<char, usize>,
+}
+
+/// State of the line being read
+#[derive(Default)]
+struct State {
+    stack: Stack,
+    column: usize,
+    corrupt: Option<Diagnostic>,
+    /// Bytes of a character split across reads
+    pending: Vec<u8>,
+}
+
+/// Validate lines straight from a reader without holding whole lines in memory, yielding each
+/// line as soon as its end is reached
+///
+/// Carriage returns are ignored so that CRLF input behaves like `BufRead::lines`.
+pub struct Validator<'a, R> {
+    set: &'a BracketSet,
+    reader: BufReader<R>,
+    state: State,
+    stats: Stats,
+    done: bool,
+}
+
+impl<'a, R: Read> Validator<'a, R> {
+    pub fn new(set: &'a BracketSet, reader: R) -> Self {
+        Self {
+            set,
+            reader: BufReader::with_capacity(1 << 16, reader),
+            state: State::default(),
+            stats: Stats::default(),
+            done: false,
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn push(set: &BracketSet, state: &mut State, stats: &mut Stats, byte: u8) -> io::Result<()> {
+        if state.corrupt.is_some() || byte == b'\r' {
+            return Ok(());
+        }
+
+        state.pending.push(byte);
+
+        let c = match std::str::from_utf8(&state.pending) {
+            Ok(s) => s.chars().next().unwrap(),
+            Err(e) if e.error_len().is_none() => return Ok(()),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+
+        state.pending.clear();
+
+        let kind = diagnostic::step(set, &mut state.stack, state.column, c).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "line {}, column {}: {}",
+                    stats.lines + 1,
+                    state.column + 1,
+                    e
+                ),
+            )
+        })?;
+
+        if let Some(kind) = kind {
+            state.corrupt = Some(Diagnostic {
+                column: state.column,
+                kind,
+            });
+        } else if set.opened_by(c).is_some() {
+            *stats.opened.entry(c).or_default() += 1;
+            stats.max_depth = stats.max_depth.max(state.stack.len());
+        }
+
+        state.column += 1;
+        Ok(())
+    }
+
+    fn finish_line(&mut self) -> io::Result<Line> {
+        let state = std::mem::take(&mut self.state);
+        self.stats.lines += 1;
+
+        if !state.pending.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "line ends inside a character",
+            ));
+        }
+
+        Ok(match state.corrupt {
+            Some(diagnostic) => Line::Corrupt(diagnostic),
+            None if state.stack.is_empty() => Line::Good,
+            None => Line::Incomplete(state.stack.iter().map(|&(_, c)| c).collect()),
+        })
+    }
+}
+
+impl<'a, R: Read> Iterator for Validator<'a, R> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let buf = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            if buf.is_empty() {
+                self.done = true;
+
+                // Last line without a trailing newline
+                if self.state.column > 0 || !self.state.pending.is_empty() {
+                    return Some(self.finish_line());
+                }
+
+                break;
+            }
+
+            let mut used = 0;
+            let mut end_of_line = false;
+
+            for &byte in buf {
+                used += 1;
+
+                if byte == b'\n' {
+                    end_of_line = true;
+                    break;
+                }
+
+                if let Err(e) = Self::push(self.set, &mut self.state, &mut self.stats, byte) {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+
+            self.reader.consume(used);
+
+            if end_of_line {
+                return Some(self.finish_line());
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader handing out one byte at a time to split characters across reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&byte, rest)) if !buf.is_empty() => {
+                    buf[0] = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validator() -> io::Result<()> {
+        let set = BracketSet::default();
+        let mut validator = Validator::new(&set, "([])\r\n([]\n\n([)]\n<<".as_bytes());
+        let lines = validator.by_ref().collect::<io::Result<Vec<_>>>()?;
+
+        assert_eq!(lines.len(), 5);
+        assert!(matches!(lines[0], Line::Good));
+        assert!(matches!(&lines[1], Line::Incomplete(stack) if stack == &['(']));
+        assert!(matches!(lines[2], Line::Good));
+        assert!(matches!(lines[3], Line::Corrupt(d) if d.column == 2));
+        assert!(matches!(&lines[4], Line::Incomplete(stack) if stack.len() == 2));
+
+        let stats = validator.stats();
+        assert_eq!(stats.lines, 5);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.opened[&'('], 3);
+        assert_eq!(stats.opened[&'<'], 2);
+
+        let quotes: BracketSet = "« » 7 1".parse().unwrap();
+        let lines = Validator::new(&quotes, Trickle("««»\n»".as_bytes()))
+            .collect::<io::Result<Vec<_>>>()?;
+        assert!(matches!(&lines[0], Line::Incomplete(stack) if stack == &['«']));
+        assert!(matches!(lines[1], Line::Corrupt(d) if d.found() == Some('»')));
+
+        let mut invalid = Validator::new(&set, "()\n(x)\n()".as_bytes());
+        assert!(invalid.next().unwrap().is_ok());
+        assert!(invalid.next().unwrap().is_err());
+        assert!(invalid.next().is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use crate::Grid;
+use std::io::{self, Write};
+
+/// Move the cursor home so that the next frame overwrites the previous one
+pub const HOME: &str = "\x1b[H";
+pub const CLEAR: &str = "\x1b[2J";
+
+/// Grey level of an octopus, ones that just flashed are drawn brightest
+fn shade(energy: u8) -> u8 {
+    match energy {
+        0 => 255,
+        e => 20 + e.min(9) * 18,
+    }
+}
+
+/// Grid drawn as two coloured spaces per octopus, flashing octopuses in bright yellow
+pub fn frame(grid: &Grid) -> String {
+    let mut result = String::new();
+
+    for row in &grid.energy {
+        for &energy in row {
+            let (r, g, b) = match energy {
+                0 => (255, 255, 80),
+                e => (shade(e), shade(e), shade(e)),
+            };
+
+            result.push_str(&format!("\x1b[48;2;{};{};{}m  ", r, g, b));
+        }
+
+        result.push_str("\x1b[0m\n");
+    }
+
+    result
+}
+
+/// Write a binary PGM with each octopus drawn as a `scale` by `scale` square
+pub fn write_pgm<W: Write>(grid: &Grid, scale: usize, out: &mut W) -> io::Result<()> {
+    write!(
+        out,
+        "P5\n{} {}\n255\n",
+        grid.width * scale,
+        grid.height * scale
+    )?;
+
+    for row in &grid.energy {
+        let pixels = row
+            .iter()
+            .flat_map(|&energy| (0..scale).map(move |_| shade(energy)))
+            .collect::<Vec<_>>();
+
+        for _ in 0..scale {
+            out.write_all(&pixels)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Cursor};
+
+    #[test]
+    fn test_frames() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new("9119\n1111\n1111");
+        let mut grid = Grid::new(&mut cursor.lines())?;
+        grid.step();
+
+        let frame = frame(&grid);
+        assert_eq!(frame.lines().count(), 3);
+        assert_eq!(frame.matches("\x1b[48;2;255;255;80m").count(), 2);
+
+        let mut pgm = vec![];
+        write_pgm(&grid, 2, &mut pgm)?;
+        assert!(pgm.starts_with(b"P5\n8 6\n255\n"));
+        assert_eq!(pgm.len(), b"P5\n8 6\n255\n".len() + 8 * 6);
+        assert_eq!(pgm[b"P5\n8 6\n255\n".len()], 255);
+        Ok(())
+    }
+}
@@ -1,7 +1,12 @@
+mod animate;
+
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 struct ParseError;
@@ -14,86 +19,104 @@ impl fmt::Display for ParseError {
 
 impl Error for ParseError {}
 
-enum Neighborhood {
-    Corner([(usize, usize); 3]),
-    Border([(usize, usize); 5]),
-    Inside([(usize, usize); 8]),
+/// Offsets of the eight surrounding cells
+const NEIGHBORS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// What happened during a single step
+#[derive(Debug, Default, Clone, PartialEq)]
+struct StepStats {
+    flashes: u32,
+    /// Number of flash rounds, octopuses charged by the energy increase alone make up the first
+    depth: usize,
+    /// First octopus to flash in reading order
+    first: Option<(usize, usize)>,
 }
 
-impl Neighborhood {
-    fn points(&self) -> &[(usize, usize)] {
-        match self {
-            Neighborhood::Corner(data) => data,
-            Neighborhood::Border(data) => data,
-            Neighborhood::Inside(data) => data,
-        }
-    }
+/// Repeating part of the sequence of grid states
+#[derive(Debug)]
+struct Cycle {
+    /// Number of steps before the first state that repeats
+    start: usize,
+    length: usize,
+    /// Total flashes after each number of steps up to the end of the first cycle
+    flashes: Vec<u64>,
+}
 
-    fn new(x: usize, y: usize) -> Neighborhood {
-        match (x, y) {
-            (0, 0) => Neighborhood::Corner([(0, 1), (1, 0), (1, 1)]),
-            (0, 9) => Neighborhood::Corner([(0, 8), (1, 8), (1, 9)]),
-            (9, 0) => Neighborhood::Corner([(8, 0), (8, 1), (9, 1)]),
-            (9, 9) => Neighborhood::Corner([(9, 8), (8, 9), (8, 8)]),
-            (x, 0) => {
-                Neighborhood::Border([(x - 1, 0), (x - 1, 1), (x, 1), (x + 1, 1), (x + 1, 0)])
-            }
-            (x, 9) => {
-                Neighborhood::Border([(x - 1, 9), (x - 1, 8), (x, 8), (x + 1, 8), (x + 1, 9)])
-            }
-            (0, y) => {
-                Neighborhood::Border([(0, y - 1), (1, y - 1), (1, y), (1, y + 1), (0, y + 1)])
-            }
-            (9, y) => {
-                Neighborhood::Border([(9, y - 1), (8, y - 1), (8, y), (8, y + 1), (9, y + 1)])
-            }
-            (x, y) => Neighborhood::Inside([
-                (x - 1, y - 1),
-                (x - 1, y),
-                (x - 1, y + 1),
-                (x, y - 1),
-                (x, y + 1),
-                (x + 1, y - 1),
-                (x + 1, y),
-                (x + 1, y + 1),
-            ]),
+impl Cycle {
+    /// Total flashes after any number of steps
+    fn total_flashes(&self, num_steps: u64) -> u64 {
+        if num_steps < self.flashes.len() as u64 {
+            return self.flashes[num_steps as usize];
         }
+
+        let (start, length) = (self.start as u64, self.length as u64);
+        let per_cycle = self.flashes[self.start + self.length] - self.flashes[self.start];
+        let (cycles, rest) = ((num_steps - start) / length, (num_steps - start) % length);
+
+        self.flashes[(start + rest) as usize] + cycles * per_cycle
     }
 }
 
 #[derive(Clone)]
 struct Grid {
-    energy: [[u8; 10]; 10],
+    width: usize,
+    height: usize,
+    /// Energy levels by row
+    energy: Vec<Vec<u8>>,
 }
 
 impl Grid {
     fn new<B: BufRead>(lines: &mut Lines<B>) -> Result<Self, ParseError> {
-        let mut energy = [[0u8; 10]; 10];
-
-        for y in 0..10 {
-            let line = lines
-                .next()
-                .ok_or_else(|| ParseError {})?
-                .map_err(|_| ParseError {})?;
-            let mut chars = line.chars();
-
-            for x in 0..10 {
-                let c = chars.next().ok_or_else(|| ParseError {})?;
-                match c {
-                    '0'..='9' => energy[x][y] = c.to_digit(10).ok_or_else(|| ParseError {})? as u8,
-                    _ => return Err(ParseError {}),
-                }
-            }
+        let energy = lines
+            .map(|line| {
+                line.map_err(|_| ParseError {})?
+                    .chars()
+                    .map(|c| c.to_digit(10).map(|d| d as u8).ok_or(ParseError {}))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let width = energy.first().map_or(0, |row| row.len());
+
+        if width == 0 || energy.iter().any(|row| row.len() != width) {
+            return Err(ParseError {});
         }
 
-        Ok(Grid { energy })
+        Ok(Grid {
+            width,
+            height: energy.len(),
+            energy,
+        })
+    }
+
+    fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width as isize, self.height as isize);
+
+        NEIGHBORS.iter().filter_map(move |(dx, dy)| {
+            let (x, y) = (x as isize + dx, y as isize + dy);
+
+            if x >= 0 && y >= 0 && x < width && y < height {
+                Some((x as usize, y as usize))
+            } else {
+                None
+            }
+        })
     }
 
     fn charged(&self) -> Option<Vec<(usize, usize)>> {
-        let charged = (0..10)
-            .flat_map(|x| {
-                (0..10).filter_map(move |y| {
-                    if self.energy[x][y] > 9 {
+        let charged = (0..self.height)
+            .flat_map(|y| {
+                (0..self.width).filter_map(move |x| {
+                    if self.energy[y][x] > 9 {
                         Some((x, y))
                     } else {
                         None
@@ -109,23 +132,47 @@ impl Grid {
         }
     }
 
-    fn step(&mut self) -> u32 {
+    /// Whether every octopus flashed in the last step
+    fn synchronised(&self) -> bool {
+        self.energy.iter().flatten().all(|&e| e == 0)
+    }
+
+    /// Grid of the given size with random energy levels
+    fn random(width: usize, height: usize, mut seed: u64) -> Self {
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 10) as u8
+        };
+
+        let energy = (0..height)
+            .map(|_| (0..width).map(|_| next()).collect())
+            .collect();
+
+        Grid {
+            width,
+            height,
+            energy,
+        }
+    }
+
+    /// Step by rescanning the whole grid for charged octopuses after each round of flashes
+    fn step_rescan(&mut self) -> u32 {
         let mut flashes = 0;
 
-        for x in 0..10 {
-            for y in 0..10 {
-                self.energy[x][y] += 1;
-            }
+        for energy in self.energy.iter_mut().flatten() {
+            *energy += 1;
         }
 
         while let Some(charged) = self.charged() {
             for (x, y) in charged {
                 flashes += 1;
-                self.energy[x][y] = 0;
+                self.energy[y][x] = 0;
 
-                for (x, y) in Neighborhood::new(x, y).points() {
-                    if self.energy[*x][*y] > 0 {
-                        self.energy[*x][*y] += 1;
+                for (x, y) in self.neighbors(x, y) {
+                    if self.energy[y][x] > 0 {
+                        self.energy[y][x] += 1;
                     }
                 }
             }
@@ -134,14 +181,99 @@ impl Grid {
         flashes
     }
 
+    /// Step by queueing octopuses as soon as they become charged, so that flashes only touch
+    /// their neighbors
+    fn step_stats(&mut self) -> StepStats {
+        let mut stats = StepStats::default();
+        let mut queue = VecDeque::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.energy[y][x] += 1;
+
+                if self.energy[y][x] > 9 {
+                    queue.push_back((x, y, 1));
+                }
+            }
+        }
+
+        stats.first = queue.front().map(|&(x, y, _)| (x, y));
+
+        while let Some((x, y, depth)) = queue.pop_front() {
+            stats.flashes += 1;
+            stats.depth = depth;
+            self.energy[y][x] = 0;
+
+            for (nx, ny) in self.neighbors(x, y) {
+                let energy = &mut self.energy[ny][nx];
+
+                // Only the increase to ten queues a neighbor, flashed ones stay at zero
+                if *energy > 0 {
+                    *energy += 1;
+
+                    if *energy == 10 {
+                        queue.push_back((nx, ny, depth + 1));
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+
+    fn step(&mut self) -> u32 {
+        self.step_stats().flashes
+    }
+
     fn solve_part_one(&mut self, num_steps: usize) -> u32 {
         (0..num_steps).map(|_| self.step()).sum::<u32>()
     }
 
+    /// Energy levels packed two to a byte, enough to tell grid states apart
+    fn packed(&self) -> Vec<u8> {
+        self.energy
+            .iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|pair| pair.iter().fold(0, |byte, &&energy| byte << 4 | energy))
+            .collect()
+    }
+
+    /// Step a copy of the grid until a state repeats, giving up after `max_steps` steps
+    fn find_cycle(&self, max_steps: usize) -> Option<Cycle> {
+        let mut grid = self.clone();
+        let mut seen = HashMap::new();
+        let mut flashes = vec![0];
+
+        seen.insert(grid.packed(), 0);
+
+        for steps in 1..=max_steps {
+            let total = flashes[steps - 1] + grid.step() as u64;
+            flashes.push(total);
+
+            let state = grid.packed();
+
+            if let Some(&start) = seen.get(&state) {
+                return Some(Cycle {
+                    start,
+                    length: steps - start,
+                    flashes,
+                });
+            }
+
+            seen.insert(state, steps);
+        }
+
+        None
+    }
+
     fn solve_part_two(&mut self) -> u32 {
         let mut step = 1;
+        self.step();
 
-        while self.step() != 100 {
+        while !self.synchronised() {
+            self.step();
             step += 1;
         }
 
@@ -149,9 +281,129 @@ impl Grid {
     }
 }
 
+/// Show the steps in `range` in the terminal and write them as numbered PGM files to `frames`
+fn animate(
+    mut grid: Grid,
+    (from, to): (usize, usize),
+    delay: Duration,
+    terminal: bool,
+    frames: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    for _ in 0..from {
+        grid.step();
+    }
+
+    if terminal {
+        write!(out, "{}", animate::CLEAR)?;
+    }
+
+    for step in from..=to {
+        let flashes = if step > from { grid.step() } else { 0 };
+
+        if terminal {
+            write!(out, "{}{}", animate::HOME, animate::frame(&grid))?;
+            writeln!(out, "step {} flashes {}", step, flashes)?;
+            out.flush()?;
+            std::thread::sleep(delay);
+        }
+
+        if let Some(directory) = &frames {
+            let path = directory.join(format!("frame-{:05}.pgm", step));
+            animate::write_pgm(&grid, 8, &mut BufWriter::new(File::create(path)?))?;
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reader = BufReader::new(File::open("input")?);
     let mut grid = Grid::new(&mut reader.lines())?;
+    let mut steps = None;
+    let mut cycle_limit = 100_000;
+    let mut stats = None;
+    let mut bench = None;
+    let mut animation = false;
+    let mut delay = 100;
+    let mut range = (0, 100);
+    let mut frames = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--steps" => steps = Some(args.next().ok_or("--steps requires a count")?.parse()?),
+            "--cycle-limit" => {
+                cycle_limit = args
+                    .next()
+                    .ok_or("--cycle-limit requires a count")?
+                    .parse()?
+            }
+            "--stats" => stats = Some(args.next().ok_or("--stats requires a count")?.parse()?),
+            "--bench" => bench = Some(args.next().ok_or("--bench requires a size")?.parse()?),
+            "--animate" => animation = true,
+            "--delay" => {
+                delay = args
+                    .next()
+                    .ok_or("--delay requires milliseconds")?
+                    .parse()?
+            }
+            "--from" => range.0 = args.next().ok_or("--from requires a step")?.parse()?,
+            "--to" => range.1 = args.next().ok_or("--to requires a step")?.parse()?,
+            "--frames" => {
+                frames = Some(PathBuf::from(
+                    args.next().ok_or("--frames requires a directory")?,
+                ))
+            }
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+
+    if animation || frames.is_some() {
+        return animate(grid, range, Duration::from_millis(delay), animation, frames);
+    }
+
+    if let Some(size) = bench {
+        let grid = Grid::random(size, size, 0x2545_f491_4f6c_dd1d);
+
+        let start = Instant::now();
+        let mut rescan = grid.clone();
+        let rescan_flashes = (0..100).map(|_| rescan.step_rescan()).sum::<u32>();
+        let rescan_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut worklist = grid;
+        let flashes = (0..100).map(|_| worklist.step()).sum::<u32>();
+        let worklist_time = start.elapsed();
+
+        println!("rescan   {} flashes in {:?}", rescan_flashes, rescan_time);
+        println!("worklist {} flashes in {:?}", flashes, worklist_time);
+        return Ok(());
+    }
+
+    if let Some(num_steps) = stats {
+        let mut grid = grid.clone();
+
+        for step in 1..=num_steps {
+            let stats = grid.step_stats();
+            let first = stats
+                .first
+                .map_or("-".to_string(), |(x, y)| format!("{},{}", x, y));
+            println!("{} {} {} {}", step, stats.flashes, stats.depth, first);
+        }
+    }
+
+    if let Some(steps) = steps {
+        let cycle = grid
+            .find_cycle(cycle_limit)
+            .ok_or(format!("No repeating state within {} steps", cycle_limit))?;
+        println!("cycle start {} length {}", cycle.start, cycle.length);
+        println!("{}", cycle.total_flashes(steps));
+        return Ok(());
+    }
+
     println!("{}", grid.clone().solve_part_one(100));
     println!("{}", grid.solve_part_two());
 
@@ -183,4 +435,75 @@ mod tests {
         assert_eq!(grid.solve_part_two(), 195);
         Ok(())
     }
+
+    #[test]
+    fn test_step_stats() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new("11111\n19991\n19191\n19991\n11111");
+        let mut grid = Grid::new(&mut cursor.lines())?;
+
+        // Ring of nines flashes first, then the centre it charged
+        let stats = grid.step_stats();
+        assert_eq!(stats.flashes, 9);
+        assert_eq!(stats.depth, 2);
+        assert_eq!(stats.first, Some((1, 1)));
+        assert_eq!(grid.step_stats(), StepStats::default());
+
+        for seed in 1..20 {
+            let mut worklist = Grid::random(17, 9, seed);
+            let mut rescan = worklist.clone();
+
+            for _ in 0..50 {
+                assert_eq!(worklist.step(), rescan.step_rescan());
+                assert_eq!(worklist.energy, rescan.energy);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cycle() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new(
+            "5483143223\n2745854711\n5264556173\n6141336146\n6357385478\n\
+             4167524645\n2176841721\n6882881134\n4846848554\n5283751526",
+        );
+        let grid = Grid::new(&mut cursor.lines())?;
+        let cycle = grid.find_cycle(205).ok_or("no cycle")?;
+        assert_eq!((cycle.start, cycle.length), (195, 10));
+        assert!(grid.find_cycle(204).is_none());
+
+        let mut stepped = grid.clone();
+        let mut total = 0;
+
+        for num_steps in 0..1000 {
+            assert_eq!(cycle.total_flashes(num_steps), total);
+            total += stepped.step() as u64;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_square() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new("11111\n19991\n19191\n19991\n11111");
+        let mut grid = Grid::new(&mut cursor.lines())?;
+
+        let cursor = Cursor::new("999\n999\n999\n999\n999");
+        let mut tall = Grid::new(&mut cursor.lines())?;
+        assert_eq!((tall.width, tall.height), (3, 5));
+        assert_eq!(tall.step(), 15);
+        assert!(tall.synchronised());
+
+        let cursor = Cursor::new("8888888\n8888888");
+        let mut wide = Grid::new(&mut cursor.lines())?;
+        assert_eq!((wide.width, wide.height), (7, 2));
+        assert_eq!(wide.clone().solve_part_two(), 2);
+        assert_eq!(wide.step(), 0);
+        assert_eq!(wide.step(), 14);
+
+        assert_eq!(grid.step(), 9);
+        assert_eq!(grid.energy[2][2], 0);
+        assert!(Grid::new(&mut Cursor::new("123\n12").lines()).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,57 @@
+use crate::{Graph, Node};
+use std::io::{self, Write};
+
+/// Write the caves as an undirected Graphviz graph, with big caves as boxes and the start and
+/// end highlighted
+pub fn write_dot<W: Write>(graph: &Graph, out: &mut W) -> io::Result<()> {
+    writeln!(out, "graph caves {{")?;
+
+    for node in &graph.nodes {
+        let attributes = match node {
+            Node::Start => "shape=doublecircle, style=filled, fillcolor=palegreen",
+            Node::End => "shape=doublecircle, style=filled, fillcolor=lightcoral",
+            Node::BigCave(_) => "shape=box",
+            Node::SmallCave(_) => "shape=circle",
+        };
+
+        writeln!(out, "    \"{}\" [{}];", node, attributes)?;
+    }
+
+    let mut edges = graph
+        .edges
+        .iter()
+        .filter(|(a, b)| a < b)
+        .collect::<Vec<_>>();
+    edges.sort();
+    edges.dedup();
+
+    for &&(a, b) in &edges {
+        writeln!(out, "    \"{}\" -- \"{}\";", graph.node(a), graph.node(b))?;
+    }
+
+    writeln!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Cursor};
+
+    #[test]
+    fn test_dot() -> Result<(), Box<dyn std::error::Error>> {
+        let cursor = Cursor::new("start-A\nA-b\nb-A\nA-end");
+        let graph = Graph::new(&mut cursor.lines())?;
+
+        let mut dot = vec![];
+        write_dot(&graph, &mut dot)?;
+        let dot = String::from_utf8(dot)?;
+
+        assert!(dot.starts_with("graph caves {\n"));
+        assert!(dot.contains("\"A\" [shape=box];"));
+        assert!(dot.contains("\"b\" [shape=circle];"));
+        assert!(dot.contains("\"start\" [shape=doublecircle, style=filled, fillcolor=palegreen];"));
+        assert_eq!(dot.matches(" -- ").count(), 3);
+        assert!(dot.ends_with("}\n"));
+        Ok(())
+    }
+}
@@ -1,8 +1,13 @@
+mod export;
+mod policy;
+
+use policy::Policy;
+use std::collections::HashMap;
 use std::convert::From;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Lines};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
 
 #[derive(Debug, Clone)]
 struct ParseError;
@@ -15,6 +20,25 @@ impl fmt::Display for ParseError {
 
 impl Error for ParseError {}
 
+/// Visit counters of the limited caves do not fit into the search state
+#[derive(Debug, Clone, PartialEq)]
+struct CapacityError {
+    caves: usize,
+    bits: usize,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Visits of {} caves at {} bits each do not fit into 128 bits",
+            self.caves, self.bits
+        )
+    }
+}
+
+impl Error for CapacityError {}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 enum Node {
     Start,
@@ -39,6 +63,16 @@ impl From<&str> for Node {
     }
 }
 
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Start => write!(f, "start"),
+            Node::End => write!(f, "end"),
+            Node::BigCave(name) | Node::SmallCave(name) => write!(f, "{}", name),
+        }
+    }
+}
+
 fn parse_line(line: &str) -> Result<(Node, Node), ParseError> {
     let mut split = line.split('-');
     Ok((
@@ -53,22 +87,57 @@ struct Graph {
     edges: Vec<(usize, usize)>,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
-enum PathNode {
-    Start(usize),
-    End(usize),
-    SmallCave(usize),
-    BigCave(usize),
+/// Path counts by current node and visits so far
+type Memo = HashMap<(usize, u128), usize>;
+
+/// Visit policy resolved against the nodes of a graph, visits of each cave with limited visits
+/// are counted in `bits` bits of a `u128` at the slot of the cave
+struct Search<'a> {
+    policy: &'a Policy,
+    bits: usize,
+    forbidden: Vec<bool>,
+    /// Counter slot of every node, `None` for nodes whose visits are not counted
+    slots: Vec<Option<usize>>,
+    /// Counter slots of the small caves
+    small_caves: Vec<usize>,
 }
 
-impl PathNode {
-    fn index(&self) -> usize {
-        match *self {
-            PathNode::Start(index) => index,
-            PathNode::End(index) => index,
-            PathNode::SmallCave(index) => index,
-            PathNode::BigCave(index) => index,
-        }
+impl Search<'_> {
+    fn visits(&self, visits: u128, slot: usize) -> usize {
+        (visits >> (slot * self.bits) & ((1 << self.bits) - 1)) as usize
+    }
+
+    /// Number of small caves visited more often than small caves usually may
+    fn extra_caves(&self, visits: u128) -> usize {
+        self.small_caves
+            .iter()
+            .filter(|&&slot| self.visits(visits, slot) > self.policy.small)
+            .count()
+    }
+
+    /// Visits after entering a cave, `None` if the policy does not allow it
+    fn enter(&self, visits: u128, index: usize, node: &Node) -> Option<u128> {
+        let slot = self.slots[index];
+        let count = slot.map_or(0, |slot| self.visits(visits, slot));
+        let policy = self.policy;
+
+        let allowed = !self.forbidden[index]
+            && match node {
+                Node::BigCave(_) => match policy.big {
+                    Some(limit) => count < limit,
+                    None => return Some(visits),
+                },
+                Node::SmallCave(_) => {
+                    count < policy.small
+                        || count < policy.extra
+                            && (count > policy.small
+                                || self.extra_caves(visits) < policy.extra_caves)
+                }
+                Node::Start | Node::End => false,
+            };
+
+        slot.filter(|_| allowed)
+            .map(|slot| visits + (1 << (slot * self.bits)))
     }
 }
 
@@ -120,120 +189,192 @@ impl Graph {
         &self.nodes[index]
     }
 
-    /// Find number of paths from start index to end Node, visiting small caves only once
-    fn search_once(&self) -> usize {
-        let mut candidates = vec![vec![self.start()]];
-        let mut paths = vec![];
+    /// Count paths from `index` to the end node without building them
+    fn count_paths(&self, index: usize, visits: u128, search: &Search, memo: &mut Memo) -> usize {
+        if let Some(&count) = memo.get(&(index, visits)) {
+            return count;
+        }
 
-        while !candidates.is_empty() {
-            let mut new_candidates = vec![];
-
-            for candidate in &candidates {
-                let last = candidate.last().unwrap();
-
-                for index in self.adjacent(*last) {
-                    match self.node(index) {
-                        Node::End => {
-                            let mut path = candidate.clone();
-                            path.push(index);
-                            paths.push(path);
-                        }
-                        Node::Start => {
-                            // We are back, so drop this path
-                            continue;
-                        }
-                        Node::SmallCave(_) => {
-                            // Only consider if we haven't visited the small cave yet
-                            if !candidate.contains(&index) {
-                                let mut path = candidate.clone();
-                                path.push(index);
-                                new_candidates.push(path);
-                            }
-                        }
-                        Node::BigCave(_) => {
-                            let mut path = candidate.clone();
-                            path.push(index);
-                            new_candidates.push(path);
-                        }
-                    }
+        let count = self
+            .adjacent(index)
+            .into_iter()
+            .map(|next| match self.node(next) {
+                Node::End => 1,
+                Node::Start => 0,
+                node => search
+                    .enter(visits, next, node)
+                    .map_or(0, |visits| self.count_paths(next, visits, search, memo)),
+            })
+            .sum();
+
+        memo.insert((index, visits), count);
+        count
+    }
+
+    fn search<'a>(&self, policy: &'a Policy) -> Result<Search<'a>, CapacityError> {
+        let limit = policy.small.max(policy.extra).max(policy.big.unwrap_or(0));
+        let bits = (usize::BITS - limit.leading_zeros()).max(1) as usize;
+
+        let mut caves = 0;
+        let mut small_caves = vec![];
+        let slots = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let limited = match node {
+                    Node::SmallCave(_) => true,
+                    Node::BigCave(_) => policy.big.is_some(),
+                    Node::Start | Node::End => false,
+                };
+
+                if !limited {
+                    return None;
                 }
-            }
 
-            candidates = new_candidates;
+                if let Node::SmallCave(_) = node {
+                    small_caves.push(caves);
+                }
+
+                caves += 1;
+                Some(caves - 1)
+            })
+            .collect();
+
+        if caves * bits > 128 {
+            return Err(CapacityError { caves, bits });
         }
 
-        paths.iter().count()
+        Ok(Search {
+            policy,
+            bits,
+            forbidden: self
+                .nodes
+                .iter()
+                .map(|node| match node {
+                    Node::BigCave(name) | Node::SmallCave(name) => policy.forbidden.contains(name),
+                    _ => false,
+                })
+                .collect(),
+            slots,
+            small_caves,
+        })
     }
 
-    /// Find number of paths from start index to end Node, visiting a single small cave twice
-    fn search_twice(&self) -> usize {
-        let mut candidates = vec![vec![PathNode::Start(self.start())]];
-        let mut paths = vec![];
+    /// Count paths from start to end visiting caves as often as `policy` allows
+    fn count(&self, policy: &Policy) -> Result<usize, CapacityError> {
+        let search = self.search(policy)?;
+        Ok(self.count_paths(self.start(), 0, &search, &mut Memo::new()))
+    }
 
-        while !candidates.is_empty() {
-            let mut new_candidates = vec![];
-
-            for candidate in &candidates {
-                let last = candidate.last().unwrap();
-
-                for index in self.adjacent(last.index()) {
-                    match self.node(index) {
-                        Node::End => {
-                            let mut path = candidate.clone();
-                            path.push(PathNode::End(index));
-                            paths.push(path);
-                        }
-                        Node::Start => {
-                            // We are back, so drop this path
-                            continue;
-                        }
-                        Node::SmallCave(_) => {
-                            let mut smalls = candidate
-                                .iter()
-                                .filter_map(|c| {
-                                    if matches!(c, PathNode::SmallCave(_)) {
-                                        Some(c.index())
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .collect::<Vec<_>>();
-                            smalls.sort_unstable();
-
-                            let old_len = smalls.len();
-                            smalls.dedup();
-                            let have_visited_twice = old_len > 0 && smalls.len() == old_len - 1;
-
-                            // Only consider if we haven't visited the small cave yet
-                            if !have_visited_twice
-                                || !candidate.contains(&PathNode::SmallCave(index))
-                            {
-                                let mut path = candidate.clone();
-                                path.push(PathNode::SmallCave(index));
-                                new_candidates.push(path);
-                            }
-                        }
-                        Node::BigCave(_) => {
-                            let mut path = candidate.clone();
-                            path.push(PathNode::BigCave(index));
-                            new_candidates.push(path);
-                        }
-                    }
+    fn visit_paths<E>(
+        &self,
+        path: &mut Vec<usize>,
+        visits: u128,
+        search: &Search,
+        f: &mut dyn FnMut(&[usize]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        for next in self.adjacent(*path.last().unwrap()) {
+            let visits = match self.node(next) {
+                Node::Start => continue,
+                Node::End => {
+                    path.push(next);
+                    f(path)?;
+                    path.pop();
+                    continue;
                 }
-            }
-
-            candidates = new_candidates;
+                node => match search.enter(visits, next, node) {
+                    Some(visits) => visits,
+                    None => continue,
+                },
+            };
+
+            path.push(next);
+            self.visit_paths(path, visits, search, f)?;
+            path.pop();
         }
 
-        paths.iter().count()
+        Ok(())
+    }
+
+    /// Call `f` with the node indices of every path from start to end allowed by `policy`, in
+    /// depth first order
+    fn for_each_path<E: From<CapacityError>>(
+        &self,
+        policy: &Policy,
+        mut f: impl FnMut(&[usize]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let search = self.search(policy)?;
+        self.visit_paths(&mut vec![self.start()], 0, &search, &mut f)
+    }
+
+    /// Path as comma separated cave names
+    fn format_path(&self, path: &[usize]) -> String {
+        path.iter()
+            .map(|&index| self.node(index).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Find number of paths from start index to end Node, visiting small caves only once
+    fn search_once(&self) -> Result<usize, CapacityError> {
+        self.count(&Policy::once())
+    }
+
+    /// Find number of paths from start index to end Node, visiting a single small cave twice
+    fn search_twice(&self) -> Result<usize, CapacityError> {
+        self.count(&Policy::twice())
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let reader = BufReader::new(File::open("input")?);
     let graph = Graph::new(&mut reader.lines())?;
-    println!("{}", graph.search_once());
-    println!("{}", graph.search_twice());
+    let mut policy = None;
+    let mut dot = None;
+    let mut paths = false;
+    let mut paths_file = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--policy" => policy = Some(args.next().ok_or("--policy requires settings")?.parse()?),
+            "--dot" => dot = Some(args.next().ok_or("--dot requires a path")?),
+            "--paths" => paths = true,
+            "--paths-file" => paths_file = Some(args.next().ok_or("--paths-file requires a path")?),
+            _ => return Err(format!("Unknown argument {}", arg).into()),
+        }
+    }
+
+    if let Some(path) = dot {
+        export::write_dot(&graph, &mut BufWriter::new(File::create(path)?))?;
+    }
+
+    if paths || paths_file.is_some() {
+        let policy = policy.unwrap_or_else(Policy::once);
+        let mut out: Box<dyn Write> = match paths_file {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(std::io::stdout().lock()),
+        };
+
+        let mut count = 0;
+
+        graph.for_each_path(&policy, |path| -> Result<(), Box<dyn Error>> {
+            count += 1;
+            Ok(writeln!(out, "{}", graph.format_path(path))?)
+        })?;
+
+        out.flush()?;
+        eprintln!("{} paths", count);
+        return Ok(());
+    }
+
+    if let Some(policy) = policy {
+        println!("{}", graph.count(&policy)?);
+        return Ok(());
+    }
+
+    println!("{}", graph.search_once()?);
+    println!("{}", graph.search_twice()?);
     Ok(())
 }
 
@@ -261,8 +402,45 @@ b-end"#,
 
         let adjacent = graph.adjacent(start);
         assert_eq!(adjacent.len(), 2);
-        assert_eq!(graph.search_once(), 10);
-        assert_eq!(graph.search_twice(), 36);
+        assert_eq!(graph.search_once()?, 10);
+        assert_eq!(graph.search_twice()?, 36);
+        assert_eq!(graph.count(&"small=2".parse()?)?, 54);
+        assert_eq!(graph.count(&"extra=3 caves=2".parse()?)?, 267);
+        assert_eq!(graph.count(&"big=1".parse()?)?, 4);
+        assert_eq!(graph.count(&"forbid=b".parse()?)?, 2);
+
+        let mut paths = vec![];
+        graph.for_each_path(&Policy::once(), |path| {
+            paths.push(graph.format_path(path));
+            Ok::<_, Box<dyn Error>>(())
+        })?;
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            vec![
+                "start,A,b,A,c,A,end",
+                "start,A,b,A,end",
+                "start,A,b,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,A,end",
+                "start,b,A,c,A,end",
+                "start,b,A,end",
+                "start,b,end",
+            ]
+        );
+
+        for policy in ["twice", "small=2", "extra=3 caves=2", "big=1 forbid=c"] {
+            let policy = policy.parse()?;
+            let mut count = 0;
+            graph.for_each_path(&policy, |_| {
+                count += 1;
+                Ok::<_, Box<dyn Error>>(())
+            })?;
+            assert_eq!(count, graph.count(&policy)?);
+        }
 
         let cursor = Cursor::new(
             r#"dc-end
@@ -278,8 +456,8 @@ kj-dc"#,
         );
 
         let graph = Graph::new(&mut cursor.lines())?;
-        assert_eq!(graph.search_once(), 19);
-        assert_eq!(graph.search_twice(), 103);
+        assert_eq!(graph.search_once()?, 19);
+        assert_eq!(graph.search_twice()?, 103);
 
         let cursor = Cursor::new(
             r#"fs-end
@@ -303,9 +481,58 @@ start-RW"#,
         );
 
         let graph = Graph::new(&mut cursor.lines())?;
-        assert_eq!(graph.search_once(), 226);
-        assert_eq!(graph.search_twice(), 3509);
+        assert_eq!(graph.search_once()?, 226);
+        assert_eq!(graph.search_twice()?, 3509);
+
+        // Start and end need no counters, leaving room for 13 bits on each of the 8 caves
+        assert_eq!(graph.count(&"big=5000".parse()?)?, 226);
 
         Ok(())
     }
+
+    #[test]
+    fn test_many_caves() -> Result<(), Box<dyn std::error::Error>> {
+        // Two halls reached from the start, each opening onto 15 small caves
+        let mut lines = vec!["start-HA", "start-HB", "HA-end", "HB-end"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        for i in 0..15 {
+            lines.push(format!("HA-a{}", i));
+            lines.push(format!("HB-b{}", i));
+        }
+
+        let graph = Graph::new(&mut Cursor::new(lines.join("\n")).lines())?;
+        assert_eq!(graph.nodes.len(), 34);
+
+        // Any ordered selection of the small caves of one hall
+        let arrangements = (0..=15u64)
+            .map(|k| (15 - k + 1..=15).product::<u64>())
+            .sum::<u64>();
+        assert_eq!(graph.search_once()? as u64, 2 * arrangements);
+
+        // Limited halls need counters too, which no longer fit next to the small caves
+        assert_eq!(
+            graph.count(&"big=5000".parse()?),
+            Err(CapacityError {
+                caves: 32,
+                bits: 13
+            })
+        );
+
+        // More small caves than visit counters fit into the search state
+        let lines = (0..130)
+            .map(|i| format!("start-c{}", i))
+            .collect::<Vec<_>>();
+        let graph = Graph::new(&mut Cursor::new(lines.join("\n")).lines())?;
+        assert_eq!(
+            graph.search_once(),
+            Err(CapacityError {
+                caves: 130,
+                bits: 1
+            })
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyError(String);
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid visit policy setting {:?}", self.0)
+    }
+}
+
+impl Error for PolicyError {}
+
+/// How often caves may be visited on a path
+#[derive(Clone, Debug, PartialEq)]
+pub struct Policy {
+    /// Visits allowed for every small cave
+    pub small: usize,
+    /// Visits allowed for small caves granted extra visits
+    pub extra: usize,
+    /// Number of small caves that may go beyond `small` visits, up to `extra`
+    pub extra_caves: usize,
+    /// Visits allowed for every big cave, unlimited if `None`
+    pub big: Option<usize>,
+    /// Names of caves that are never entered
+    pub forbidden: Vec<String>,
+}
+
+impl Policy {
+    /// Small caves at most once
+    pub fn once() -> Self {
+        Self {
+            small: 1,
+            extra: 1,
+            extra_caves: 0,
+            big: None,
+            forbidden: vec![],
+        }
+    }
+
+    /// Small caves at most once, except for a single one that may be visited twice
+    pub fn twice() -> Self {
+        Self {
+            extra: 2,
+            extra_caves: 1,
+            ..Self::once()
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = PolicyError;
+
+    /// Parse a preset name, or space separated settings such as `small=1 extra=3 caves=2 big=4
+    /// forbid=ab,cd` applied to the `once` preset
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "once" => return Ok(Self::once()),
+            "twice" => return Ok(Self::twice()),
+            _ => {}
+        }
+
+        let mut policy = Self::once();
+
+        for setting in s.split_whitespace() {
+            let invalid = || PolicyError(setting.to_string());
+            let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+            let number = || value.parse::<usize>().map_err(|_| invalid());
+
+            match key {
+                "small" => policy.small = number()?,
+                "extra" => policy.extra = number()?,
+                "caves" => policy.extra_caves = number()?,
+                "big" => policy.big = Some(number()?),
+                "forbid" => policy.forbidden = value.split(',').map(String::from).collect(),
+                _ => return Err(invalid()),
+            }
+        }
+
+        if policy.extra < policy.small {
+            policy.extra = policy.small;
+        }
+
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<(), PolicyError> {
+        assert_eq!("twice".parse::<Policy>()?, Policy::twice());
+        assert_eq!("extra=2 caves=1".parse::<Policy>()?, Policy::twice());
+
+        let policy: Policy = "small=2 big=3 forbid=ab,cd".parse()?;
+        assert_eq!(policy.extra, 2);
+        assert_eq!(policy.big, Some(3));
+        assert_eq!(policy.forbidden, vec!["ab", "cd"]);
+
+        assert_eq!(
+            "small=x".parse::<Policy>(),
+            Err(PolicyError("small=x".to_string()))
+        );
+        assert!("often".parse::<Policy>().is_err());
+        Ok(())
+    }
+}